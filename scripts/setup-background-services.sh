@@ -4,25 +4,44 @@
 # This generates LaunchAgent plists with current system paths and configurable times
 # See README.md for usage examples
 
-# Default times
-SYNC_HOUR=14      # 2 PM
-NOTIFY_HOUR=20    # 8 PM
+PROJECT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+# the sync service runs it, and it reads the times from the app config file
+HABIT_SYNC="${HABIT_SYNC:-$PROJECT_DIR/src-tauri/target/release/habit-sync}"
+
+if [[ ! -x "$HABIT_SYNC" ]]; then
+    echo "habit-sync not found at $HABIT_SYNC, build it first:"
+    echo "  cd src-tauri && cargo build --release --bin habit-sync"
+    exit 1
+fi
+
+# Times come from the [reminders] section of the app config file, see README.md
+if ! REMINDER_TIMES=$("$HABIT_SYNC" --reminder-times); then
+    echo "Could not read the reminder times of the config file"
+    exit 1
+fi
+{ read -r SYNC_TIME; read -r -a NOTIFY_TIMES; } <<< "$REMINDER_TIMES"
+PRINT_TIMES=false
 
 # Parse command line arguments
 while [[ $# -gt 0 ]]; do
     case $1 in
         --sync-hour)
-            SYNC_HOUR="$2"
+            SYNC_TIME="$2:00"
             shift 2
             ;;
         --notify-hour)
-            NOTIFY_HOUR="$2"
+            NOTIFY_TIMES=("$2:00")
             shift 2
             ;;
+        --print-times)
+            PRINT_TIMES=true
+            shift
+            ;;
         --help|-h)
-            echo "Usage: $0 [--sync-hour HOUR] [--notify-hour HOUR]"
-            echo "  --sync-hour    Hour for calendar sync (0-23, default: 14 = 2 PM)"
-            echo "  --notify-hour  Hour for notifications (0-23, default: 20 = 8 PM)"
+            echo "Usage: $0 [--sync-hour HOUR] [--notify-hour HOUR] [--print-times]"
+            echo "  --sync-hour    Hour for calendar sync (0-23, default: reminders.sync_time of the config file, else 14 = 2 PM)"
+            echo "  --notify-hour  Hour for notifications (0-23, default: reminders.notify_times of the config file, else 20 = 8 PM)"
+            echo "  --print-times  Print the times the services would run at, without setting them up"
             exit 0
             ;;
         *)
@@ -33,14 +52,37 @@ while [[ $# -gt 0 ]]; do
     esac
 done
 
-PROJECT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+if [[ "$PRINT_TIMES" == true ]]; then
+    echo "$SYNC_TIME"
+    echo "${NOTIFY_TIMES[*]}"
+    exit 0
+fi
+
+# "HH:MM" to a StartCalendarInterval entry
+calendar_interval() {
+    local hour="${1%%:*}" minute="${1##*:}"
+    cat << EOF
+        <dict>
+            <key>Hour</key>
+            <integer>$((10#$hour))</integer>
+            <key>Minute</key>
+            <integer>$((10#$minute))</integer>
+        </dict>
+EOF
+}
+
+NOTIFY_INTERVALS=""
+for time in "${NOTIFY_TIMES[@]}"; do
+    NOTIFY_INTERVALS+="$(calendar_interval "$time")"$'\n'
+done
+
 USER_HOME="$HOME"
 LAUNCH_AGENTS_DIR="$USER_HOME/Library/LaunchAgents"
 
 echo "Setting up Habit Tracker background services..."
 echo "Project directory: $PROJECT_DIR"
-echo "Calendar sync time: ${SYNC_HOUR}:00 ($(date -j -f "%H" "${SYNC_HOUR}" "+%I %p" 2>/dev/null || echo "${SYNC_HOUR}:00"))"
-echo "Notification time: ${NOTIFY_HOUR}:00 ($(date -j -f "%H" "${NOTIFY_HOUR}" "+%I %p" 2>/dev/null || echo "${NOTIFY_HOUR}:00"))"
+echo "Calendar sync time: ${SYNC_TIME}"
+echo "Notification times: ${NOTIFY_TIMES[*]}"
 
 # Create LaunchAgents directory if it doesn't exist
 mkdir -p "$LAUNCH_AGENTS_DIR"
@@ -62,13 +104,7 @@ cat > "$LAUNCH_AGENTS_DIR/com.habittracker.notify.plist" << EOF
     
     <key>StartCalendarInterval</key>
     <array>
-        <dict>
-            <key>Hour</key>
-            <integer>$NOTIFY_HOUR</integer>
-            <key>Minute</key>
-            <integer>0</integer>
-        </dict>
-    </array>
+$NOTIFY_INTERVALS    </array>
     
     <key>RunAtLoad</key>
     <false/>
@@ -98,12 +134,7 @@ cat > "$LAUNCH_AGENTS_DIR/com.habittracker.sync.plist" << EOF
     
     <key>StartCalendarInterval</key>
     <array>
-        <dict>
-            <key>Hour</key>
-            <integer>$SYNC_HOUR</integer>
-            <key>Minute</key>
-            <integer>0</integer>
-        </dict>
+$(calendar_interval "$SYNC_TIME")
     </array>
     
     <key>RunAtLoad</key>
@@ -128,8 +159,8 @@ launchctl enable "gui/$(id -u)/com.habittracker.notify"
 launchctl enable "gui/$(id -u)/com.habittracker.sync"
 
 echo "✅ Background services setup successfully!"
-echo "📱 Notifications will appear daily at ${NOTIFY_HOUR}:00"
-echo "🔄 Calendar sync will run daily at ${SYNC_HOUR}:00"
+echo "📱 Notifications will appear daily at ${NOTIFY_TIMES[*]}"
+echo "🔄 Calendar sync will run daily at ${SYNC_TIME}"
 echo ""
 echo "To uninstall:"
 echo "  launchctl unload $LAUNCH_AGENTS_DIR/com.habittracker.*.plist"
@@ -1,17 +1,30 @@
 use crate::{
-    AppConfig, CreateHabitRequest, InsertHabitEntriesRequest, InsertHabitEntryItem,
-    UpdateHabitRequest,
+    backup::{snapshot, BackupConfig, BackupReason},
+    error::HabitTrackerError,
+    migrations::{migrate, schema_version, SCHEMA_VERSION},
+    recurrence::Recurrence,
+    validation::{
+        validate_create_habit, validate_date_range, validate_insert_habit_entries,
+        validate_update_habit, validate_update_habit_entry,
+    },
+    AppConfig, CreateHabitRequest, DateRange, InsertHabitEntriesRequest, InsertHabitEntryItem,
+    UpdateHabitEntryRequest, UpdateHabitRequest,
 };
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
 use chrono::serde::ts_seconds;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
 use rusqlite::{
     params,
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
-    Connection, Error, Result, Row, ToSql,
+    Connection, Error, OptionalExtension, Result, Row, ToSql, TransactionBehavior,
 };
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time,
+};
 
 #[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
 pub enum HabitType {
@@ -40,7 +53,37 @@ impl ToSql for HabitType {
     }
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
+/// Whether a habit is tracked as a simple yes/no or as a measured amount
+/// (e.g. glasses of water, minutes of reading) with a daily target.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum HabitKind {
+    #[default]
+    YesNo,
+    Measurable,
+}
+
+impl FromSql for HabitKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        match s {
+            "yesNo" => Ok(HabitKind::YesNo),
+            "measurable" => Ok(HabitKind::Measurable),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for HabitKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let s = match self {
+            HabitKind::YesNo => "yesNo",
+            HabitKind::Measurable => "measurable",
+        };
+        Ok(ToSqlOutput::from(s))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize, Type)]
 pub struct EventIds {
     pub values: Vec<String>,
 }
@@ -64,13 +107,140 @@ impl ToSql for EventIds {
     }
 }
 
-#[derive(Debug, PartialEq, Serialize, Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum DayOfWeek {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<DayOfWeek> for Weekday {
+    fn from(day: DayOfWeek) -> Self {
+        match day {
+            DayOfWeek::Monday => Weekday::Mon,
+            DayOfWeek::Tuesday => Weekday::Tue,
+            DayOfWeek::Wednesday => Weekday::Wed,
+            DayOfWeek::Thursday => Weekday::Thu,
+            DayOfWeek::Friday => Weekday::Fri,
+            DayOfWeek::Saturday => Weekday::Sat,
+            DayOfWeek::Sunday => Weekday::Sun,
+        }
+    }
+}
+
+impl From<Weekday> for DayOfWeek {
+    fn from(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Mon => DayOfWeek::Monday,
+            Weekday::Tue => DayOfWeek::Tuesday,
+            Weekday::Wed => DayOfWeek::Wednesday,
+            Weekday::Thu => DayOfWeek::Thursday,
+            Weekday::Fri => DayOfWeek::Friday,
+            Weekday::Sat => DayOfWeek::Saturday,
+            Weekday::Sun => DayOfWeek::Sunday,
+        }
+    }
+}
+
+/// When a daily habit is due. Quota schedules (`TimesPerWeek`, `TimesPerMonth`) are due
+/// every day of their period until the quota is met.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize, Type)]
+pub enum Schedule {
+    #[default]
+    EveryDay,
+    Weekdays {
+        days: Vec<DayOfWeek>,
+    },
+    EveryNDays {
+        interval: u32,
+        start: NaiveDate,
+    },
+    TimesPerWeek {
+        count: u32,
+    },
+    TimesPerMonth {
+        count: u32,
+    },
+}
+
+impl Schedule {
+    /// Whether the habit is due on `day`, given the days it was completed on. For past
+    /// days of a quota schedule, a day that was skipped only counts as due once the
+    /// quota could no longer be met without it, so skipping Monday of a three times
+    /// a week habit is not a miss when it is done on three other days that week.
+    /// Weeks start on `week_start`.
+    pub fn is_due(
+        &self,
+        day: NaiveDate,
+        completed_days: &HashSet<NaiveDate>,
+        today: NaiveDate,
+        week_start: DayOfWeek,
+    ) -> bool {
+        let within_quota = |count: u32, granularity: StatsGranularity| {
+            let (_, period_start, period_end) = granularity.period(day, week_start);
+            let completed_before = period_start
+                .iter_days()
+                .take_while(|d| *d < day)
+                .filter(|d| completed_days.contains(d))
+                .count() as i64;
+            let count = i64::from(count);
+            if completed_before >= count {
+                false
+            } else if completed_days.contains(&day) || day >= today {
+                true
+            } else {
+                completed_before + (period_end - day).num_days() < count
+            }
+        };
+        match self {
+            Schedule::EveryDay => true,
+            Schedule::Weekdays { days } => days.contains(&day.weekday().into()),
+            Schedule::EveryNDays { interval, start } => {
+                day >= *start && (day - *start).num_days() % i64::from((*interval).max(1)) == 0
+            }
+            Schedule::TimesPerWeek { count } => within_quota(*count, StatsGranularity::Week),
+            Schedule::TimesPerMonth { count } => within_quota(*count, StatsGranularity::Month),
+        }
+    }
+}
+
+impl FromSql for Schedule {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str_or_null()? {
+            Some(json) => serde_json::from_str(json).map_err(|_| FromSqlError::InvalidType),
+            None => Ok(Schedule::EveryDay),
+        }
+    }
+}
+
+impl ToSql for Schedule {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let json =
+            serde_json::to_string(self).map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
 pub struct Habit {
     pub id: i64,
     pub habit_type: HabitType,
     pub event_ids: EventIds,
     pub title: String,
     pub question: String,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub kind: HabitKind,
+    pub unit: Option<String>,
+    pub target: Option<f64>,
+    pub schedule: Schedule,
+    /// Linked calendar events that disappeared from the calendar, set by syncs until the
+    /// habit is next updated so the user can relink it.
+    #[serde(default)]
+    pub removed_event_ids: EventIds,
 }
 
 impl Habit {
@@ -81,16 +251,37 @@ impl Habit {
             event_ids: row.get("eventIds")?,
             title: row.get("title")?,
             question: row.get("question")?,
+            archived_at: row.get("archivedAt")?,
+            kind: row.get("kind")?,
+            unit: row.get("unit")?,
+            target: row.get("target")?,
+            schedule: row.get("schedule")?,
+            removed_event_ids: row.get("removedEventIds")?,
         })
     }
+
+    /// Completion of an entry for this habit. Measurable habits are completed once
+    /// the recorded value reaches the daily target, the `completed` flag is ignored.
+    pub fn is_completed_by(&self, completed: bool, value: Option<f64>) -> bool {
+        match self.kind {
+            HabitKind::YesNo => completed,
+            HabitKind::Measurable => match (value, self.target) {
+                (Some(value), Some(target)) => value >= target,
+                _ => false,
+            },
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Serialize, Type)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
 pub struct HabitEntry {
     pub id: i64,
     pub habit_id: i64,
     pub completed: bool,
     pub date: DateTime<Utc>,
+    pub day: NaiveDate,
+    pub value: Option<f64>,
+    pub timezone: Option<String>,
 }
 
 impl HabitEntry {
@@ -100,11 +291,138 @@ impl HabitEntry {
             habit_id: row.get("habitId")?,
             completed: row.get("completed")?,
             date: row.get("date")?,
+            day: row.get("day")?,
+            value: row.get("value")?,
+            timezone: row.get("timezone")?,
+        })
+    }
+}
+
+/// The local calendar day a habit entry recorded at `date` counts towards.
+/// A habit has at most one entry per day. Days end at `rollover_hour` on the wall clock of
+/// `timezone` rather than after a fixed number of hours, so DST transitions do not move the
+/// boundary: with a rollover hour of 3, a check-in at 02:30 counts towards the previous day
+/// whether or not the clocks changed that night.
+pub fn local_day(date: DateTime<Utc>, timezone: Tz, rollover_hour: u32) -> NaiveDate {
+    (date.with_timezone(&timezone).naive_local() - Duration::hours(rollover_hour.into())).date()
+}
+
+/// Days between `start` and `end` (inclusive) on which a daily habit is due by its
+/// schedule, given its entries.
+pub fn scheduled_days(
+    habit: &Habit,
+    entries: &[HabitEntry],
+    start: NaiveDate,
+    end: NaiveDate,
+    today: NaiveDate,
+    week_start: DayOfWeek,
+) -> Vec<NaiveDate> {
+    let completed_days = entries
+        .iter()
+        .filter(|entry| entry.habit_id == habit.id && entry.completed)
+        .map(|entry| entry.day)
+        .collect::<HashSet<_>>();
+    start
+        .iter_days()
+        .take_while(|day| *day <= end)
+        .filter(|day| {
+            habit
+                .schedule
+                .is_due(*day, &completed_days, today, week_start)
         })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Serialize, Type)]
+pub struct HabitStreak {
+    pub habit_id: i64,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub last_break_date: Option<NaiveDate>,
+}
+
+/// Streaks over a habit's due days, oldest first. Only due days that were not completed
+/// break a streak, except for `today` which can still be completed.
+pub fn compute_streak(
+    habit_id: i64,
+    due_days: &[NaiveDate],
+    completed_days: &HashSet<NaiveDate>,
+    today: NaiveDate,
+) -> HabitStreak {
+    let mut streak = HabitStreak {
+        habit_id,
+        current_streak: 0,
+        longest_streak: 0,
+        last_break_date: None,
+    };
+    for day in due_days.iter().filter(|day| **day <= today) {
+        if completed_days.contains(day) {
+            streak.current_streak += 1;
+            streak.longest_streak = streak.longest_streak.max(streak.current_streak);
+        } else if *day < today {
+            streak.current_streak = 0;
+            streak.last_break_date = Some(*day);
+        }
+    }
+    streak
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum StatsGranularity {
+    Week,
+    Month,
+    Year,
+}
+
+impl StatsGranularity {
+    /// Label, first and last day of the period containing `day`. Weeks start on
+    /// `week_start` and are labelled with the ISO week most of their days fall in, so
+    /// weeks starting on Monday are ISO weeks.
+    pub fn period(&self, day: NaiveDate, week_start: DayOfWeek) -> (String, NaiveDate, NaiveDate) {
+        match self {
+            StatsGranularity::Week => {
+                let start = day.week(week_start.into()).first_day();
+                let week = (start + Duration::days(3)).iso_week();
+                (
+                    format!("{}-W{:02}", week.year(), week.week()),
+                    start,
+                    start + Duration::days(6),
+                )
+            }
+            StatsGranularity::Month => {
+                let start = day.with_day(1).unwrap();
+                (
+                    format!("{}-{:02}", day.year(), day.month()),
+                    start,
+                    start + Months::new(1) - Duration::days(1),
+                )
+            }
+            StatsGranularity::Year => (
+                day.year().to_string(),
+                NaiveDate::from_ymd_opt(day.year(), 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(day.year(), 12, 31).unwrap(),
+            ),
+        }
     }
 }
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
+#[derive(Debug, PartialEq, Serialize, Type)]
+pub struct HabitStatsBucket {
+    pub period: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub completed_count: u32,
+    pub due_count: u32,
+    pub completion_rate: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Type)]
+pub struct HabitStats {
+    pub habit_id: i64,
+    pub granularity: StatsGranularity,
+    pub buckets: Vec<HabitStatsBucket>,
+}
+
 #[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
 pub struct AppleCalendarEvent {
     pub id: String,
@@ -112,9 +430,60 @@ pub struct AppleCalendarEvent {
     #[serde(deserialize_with = "ts_seconds::deserialize")]
     pub start_date: DateTime<Utc>,
     pub recurrence: String,
+    #[serde(default)]
+    pub excluded_dates: ExcludedDates,
+    /// The recurring event one of whose occurrences this event moves. Habits linked to the
+    /// parent are due on it, iCalendar files list such events with a `RECURRENCE-ID`.
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Parsed from `recurrence` whenever the event is stored.
+    #[serde(default)]
+    pub recurrence_rule: Option<Recurrence>,
+    /// Set instead of `recurrence_rule` when `recurrence` could not be parsed, such events
+    /// can't be linked to habits.
+    #[serde(default)]
+    pub recurrence_error: Option<String>,
+    /// When the event went missing from a sync. Removed events are kept as tombstones until
+    /// a later sync brings them back.
+    #[serde(default)]
+    pub removed_at: Option<DateTime<Utc>>,
+}
+
+/// Changes made by a calendar sync, unchanged events are left out.
+#[derive(Debug, Default, PartialEq, Serialize, Type)]
+pub struct CalendarSyncReport {
+    pub added: Vec<AppleCalendarEvent>,
+    pub updated: Vec<AppleCalendarEvent>,
+    pub removed: Vec<AppleCalendarEvent>,
+    /// Habits newly linked to a removed event.
+    pub flagged_habits: Vec<Habit>,
+}
+
+/// Occurrences removed from a recurring event (`EXDATE`), as their start dates.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, Type)]
+pub struct ExcludedDates {
+    pub values: Vec<DateTime<Utc>>,
+}
+
+impl FromSql for ExcludedDates {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let json_str = value.as_str_or_null()?;
+        let values: Vec<DateTime<Utc>> = match json_str {
+            Some(v) => serde_json::from_str(v).map_err(|_| FromSqlError::InvalidType)?,
+            None => vec![],
+        };
+        Ok(ExcludedDates { values })
+    }
+}
+
+impl ToSql for ExcludedDates {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(&self.values)
+            .map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::from(json))
+    }
 }
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
 impl AppleCalendarEvent {
     pub fn from_row(row: &Row) -> Result<Self> {
         Ok(AppleCalendarEvent {
@@ -122,174 +491,706 @@ impl AppleCalendarEvent {
             name: row.get("name")?,
             start_date: row.get("startDate")?,
             recurrence: row.get("recurrence")?,
+            excluded_dates: row.get("excludedDates")?,
+            parent_id: row.get("parentId")?,
+            recurrence_rule: row.get("recurrenceRule")?,
+            recurrence_error: row.get("recurrenceError")?,
+            removed_at: row.get("removedAt")?,
         })
     }
+
+    /// Days between `start` and `end` (inclusive) the event occurs on, `local_day` mapping
+    /// its dates to days. Excluded occurrences are left out, and events whose recurrence
+    /// could not be parsed never occur.
+    pub fn occurrence_days(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        local_day: impl Fn(DateTime<Utc>) -> NaiveDate,
+    ) -> Vec<NaiveDate> {
+        let Some(rule) = &self.recurrence_rule else {
+            return vec![];
+        };
+        let excluded_days = self
+            .excluded_dates
+            .values
+            .iter()
+            .map(|date| local_day(*date))
+            .collect::<HashSet<_>>();
+        rule.occurrence_days(
+            local_day(self.start_date),
+            rule.until.map(|until| until.day(&local_day)),
+            end,
+        )
+        .into_iter()
+        .filter(|day| *day >= start && !excluded_days.contains(day))
+        .collect()
+    }
+
+    // `recurrence_rule` and `recurrence_error` are derived from `recurrence`, any values
+    // set on `self` are ignored. A tombstone with the same id is brought back.
+    pub(crate) fn upsert(&self, conn: &Connection) -> Result<()> {
+        let recurrence = self.recurrence.parse::<Recurrence>();
+        conn.prepare_cached(
+            "INSERT INTO appleCalendarEvent (id, name, startDate, recurrence, excludedDates, parentId, recurrenceRule, recurrenceError) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, startDate = excluded.startDate, recurrence = excluded.recurrence, excludedDates = excluded.excludedDates, parentId = excluded.parentId, recurrenceRule = excluded.recurrenceRule, recurrenceError = excluded.recurrenceError, removedAt = NULL",
+        )?
+        .execute(params![
+            self.id,
+            self.name,
+            self.start_date,
+            self.recurrence,
+            self.excluded_dates,
+            self.parent_id,
+            recurrence.as_ref().ok(),
+            recurrence.as_ref().err()
+        ])?;
+        Ok(())
+    }
+}
+
+/// Sets `recurrenceRule` and `recurrenceError` of events that have neither, i.e. those
+/// written before the columns were added, as `upsert` would.
+pub(crate) fn derive_recurrence_rules(conn: &Connection) -> Result<()> {
+    let events = conn
+        .prepare("SELECT id, recurrence FROM appleCalendarEvent WHERE recurrenceRule IS NULL AND recurrenceError IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>>>()?;
+    let mut statement = conn.prepare(
+        "UPDATE appleCalendarEvent SET recurrenceRule = ?1, recurrenceError = ?2 WHERE id = ?3",
+    )?;
+    for (id, recurrence) in events {
+        let recurrence = recurrence.parse::<Recurrence>();
+        statement.execute(params![
+            recurrence.as_ref().ok(),
+            recurrence.as_ref().err(),
+            id
+        ])?;
+    }
+    Ok(())
+}
+
+fn habit_not_found(id: i64) -> HabitTrackerError {
+    HabitTrackerError::NotFound {
+        message: format!("habit {id} not found"),
+    }
+}
+
+fn habit_entry_not_found(id: i64) -> HabitTrackerError {
+    HabitTrackerError::NotFound {
+        message: format!("habit entry {id} not found"),
+    }
+}
+
+// how long a write waits for another connection to the database, e.g. `habit-sync` while
+// the app is open, to finish its own
+const BUSY_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+/// Sets up every connection to the database. With write-ahead logging, reads don't block
+/// writes or the other way round, and writes wait for each other rather than failing.
+pub(crate) fn configure_connection(conn: &mut Connection) -> Result<()> {
+    // in-memory databases stay in `memory` mode
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    // a deferred transaction that reads, then writes, fails right away when another
+    // connection wrote in between, while an immediate one waits for the write lock first
+    conn.set_transaction_behavior(TransactionBehavior::Immediate);
+    Ok(())
 }
 
 #[derive(Debug)]
 pub struct HabitTrackerService {
-    conn: Connection,
+    pub(crate) conn: Connection,
+    pub(crate) timezone: Tz,
+    day_rollover_hour: u32,
+    pub(crate) week_start: DayOfWeek,
+    // `None` for in-memory databases, which have no backups
+    pub(crate) db_path: Option<PathBuf>,
+    pub(crate) backup_config: BackupConfig,
 }
 
 impl HabitTrackerService {
-    pub fn build(app_config: AppConfig) -> Result<HabitTrackerService> {
-        let mut conn = Connection::open(app_config.db_path)?;
+    /// Opens and migrates the database, backing it up first when it has pending migrations.
+    pub fn build(app_config: AppConfig) -> Result<HabitTrackerService, HabitTrackerError> {
+        let mut conn = Connection::open(&app_config.db_path)?;
+        configure_connection(&mut conn)?;
+        let db_path =
+            (app_config.db_path != ":memory:").then(|| PathBuf::from(&app_config.db_path));
+        if let Some(db_path) = &db_path {
+            // new databases have nothing to lose
+            let is_empty: bool = conn.query_row(
+                "SELECT NOT EXISTS(SELECT 1 FROM sqlite_master)",
+                [],
+                |row| row.get(0),
+            )?;
+            if !is_empty && schema_version(&conn)? < SCHEMA_VERSION {
+                snapshot(
+                    &conn,
+                    db_path,
+                    &app_config.backups,
+                    BackupReason::PreMigration,
+                )?;
+            }
+        }
+        migrate(&mut conn)?;
+        derive_recurrence_rules(&conn)?;
+        Ok(HabitTrackerService {
+            conn,
+            timezone: app_config.timezone,
+            day_rollover_hour: app_config.day_rollover_hour,
+            week_start: app_config.week_start,
+            db_path,
+            backup_config: app_config.backups,
+        })
+    }
 
-        let transaction = conn.transaction()?;
-        transaction.execute(
-            "CREATE TABLE IF NOT EXISTS habit (
-            id INTEGER PRIMARY KEY,
-            habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
-            eventIds TEXT NULL,
-            title TEXT NOT NULL,
-            question TEXT NOT NULL
-        )",
-            (),
-        )?;
-        transaction.execute(
-            "CREATE TABLE IF NOT EXISTS habitEntry (
-            id INTEGER PRIMARY KEY,
-            completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
-            date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            habitId INTEGER,
-            FOREIGN KEY(habitId) REFERENCES habit(id)
-        )",
-            (),
-        )?;
-        #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-        transaction.execute(
-            "CREATE TABLE IF NOT EXISTS appleCalendarEvent (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            startDate REAL NOT NULL,
-            endDate REAL NULL,
-            recurrence TEXT NOT NULL
-        )",
-            (),
-        )?;
-        transaction.commit()?;
+    pub fn local_day(&self, date: DateTime<Utc>) -> NaiveDate {
+        local_day(date, self.timezone, self.day_rollover_hour)
+    }
+
+    /// The local date of a calendar event date. Unlike `local_day`, days start at midnight,
+    /// as the rollover hour is about late check-ins, not about when events take place.
+    pub fn calendar_day(&self, date: DateTime<Utc>) -> NaiveDate {
+        local_day(date, self.timezone, 0)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.local_day(Utc::now())
+    }
 
-        Ok(HabitTrackerService { conn })
+    /// A time that counts towards `day`, for check-ins made for another day: local noon,
+    /// moved by the rollover hour.
+    pub fn date_on_day(&self, day: NaiveDate) -> DateTime<Utc> {
+        let noon =
+            day.and_hms_opt(12, 0, 0).unwrap() + Duration::hours(self.day_rollover_hour.into());
+        // noon plus a rollover hour may fall in a DST gap, which is at most an hour long
+        self.timezone
+            .from_local_datetime(&noon)
+            .earliest()
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(noon + Duration::hours(1)))
+                    .earliest()
+            })
+            .unwrap()
+            .with_timezone(&Utc)
     }
 
-    pub fn create_habit(&self, request: CreateHabitRequest) -> Result<Habit> {
+    pub fn create_habit(&self, request: CreateHabitRequest) -> Result<Habit, HabitTrackerError> {
+        validate_create_habit(&self.conn, &request)?;
         self.conn.execute(
-            "INSERT INTO habit (habitType, eventIds, title, question) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO habit (habitType, eventIds, title, question, kind, unit, target, schedule) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
                 request.habit_type,
                 request.event_ids,
                 request.title,
-                request.question
+                request.question,
+                request.kind,
+                request.unit,
+                request.target,
+                request.schedule
             ],
         )?;
         let id = self.conn.last_insert_rowid();
-        self.conn.query_row(
+        Ok(self.conn.query_row(
             "SELECT * FROM habit WHERE id = ?1",
             params![id],
             Habit::from_row,
-        )
+        )?)
     }
 
-    pub fn get_habit_entries(&self) -> Result<Vec<HabitEntry>> {
+    pub fn get_habit_entries(&self) -> Result<Vec<HabitEntry>, HabitTrackerError> {
         let mut statement = self.conn.prepare("SELECT * FROM habitEntry")?;
         let habit_entry_iter = statement.query_map([], HabitEntry::from_row)?;
-        habit_entry_iter.collect::<Result<Vec<_>>>()
+        Ok(habit_entry_iter.collect::<Result<Vec<_>>>()?)
     }
 
-    pub fn get_habits(&self) -> Result<Vec<Habit>> {
-        let mut statement = self.conn.prepare("SELECT * FROM habit")?;
-        let habit_iter = statement.query_map([], Habit::from_row)?;
-        habit_iter.collect::<Result<Vec<_>>>()
+    pub fn get_habit_streaks(&self) -> Result<Vec<HabitStreak>, HabitTrackerError> {
+        let today = self.today();
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM habitEntry WHERE habitId = ?1 ORDER BY day")?;
+        let mut streaks = vec![];
+        for habit in self.get_habits(false)? {
+            let entries = statement
+                .query_map(params![habit.id], HabitEntry::from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            let completed_days = entries
+                .iter()
+                .filter(|entry| entry.completed)
+                .map(|entry| entry.day)
+                .collect::<HashSet<_>>();
+            let due_days = match entries.first() {
+                Some(first_entry) => {
+                    self.due_days(&habit, &entries, first_entry.day, today, today)?
+                }
+                None => vec![],
+            };
+            streaks.push(compute_streak(habit.id, &due_days, &completed_days, today));
+        }
+        Ok(streaks)
     }
 
-    pub fn update_habit(&self, request: UpdateHabitRequest) -> Result<Habit> {
-        self.conn.execute(
-            "UPDATE habit SET habitType = ?1, eventIds = ?2, title = ?3, question = ?4 WHERE id = ?5",
+    pub fn get_habit_stats(
+        &self,
+        habit_id: i64,
+        range: DateRange,
+        granularity: StatsGranularity,
+    ) -> Result<HabitStats, HabitTrackerError> {
+        validate_date_range(&range)?;
+        let habit = self
+            .conn
+            .query_row(
+                "SELECT * FROM habit WHERE id = ?1",
+                params![habit_id],
+                Habit::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| habit_not_found(habit_id))?;
+        let entries = self
+            .conn
+            .prepare("SELECT * FROM habitEntry WHERE habitId = ?1 ORDER BY day")?
+            .query_map(params![habit_id], HabitEntry::from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        // the habit has no creation date, so it is considered due from its first entry on
+        let start = match entries.first() {
+            Some(first_entry) => range.start.max(first_entry.day),
+            None => range.start,
+        };
+        // due days follow from schedules and recurrence rules SQL can't expand, they are
+        // handed to the query in a table of this connection only
+        self.conn.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS dueDay (day TEXT PRIMARY KEY, period TEXT NOT NULL);
+            DELETE FROM temp.dueDay;",
+        )?;
+        let mut insert_due_day = self
+            .conn
+            .prepare_cached("INSERT INTO temp.dueDay (day, period) VALUES (?1, ?2)")?;
+        for day in self.due_days(&habit, &entries, start, range.end, self.today())? {
+            insert_due_day.execute(params![day, granularity.period(day, self.week_start).0])?;
+        }
+
+        let mut statement = self.conn.prepare(
+            "SELECT
+                MIN(dueDay.day) AS firstDay,
+                COUNT(*) AS dueCount,
+                COALESCE(SUM(habitEntry.completed), 0) AS completedCount
+            FROM temp.dueDay
+            LEFT JOIN habitEntry ON habitEntry.habitId = ?1 AND habitEntry.day = dueDay.day
+            GROUP BY dueDay.period
+            ORDER BY firstDay",
+        )?;
+        let buckets = statement
+            .query_map(params![habit_id], |row| {
+                let (period, period_start, period_end) =
+                    granularity.period(row.get("firstDay")?, self.week_start);
+                let due_count: u32 = row.get("dueCount")?;
+                let completed_count: u32 = row.get("completedCount")?;
+                Ok(HabitStatsBucket {
+                    period,
+                    start: period_start.max(range.start),
+                    end: period_end.min(range.end),
+                    completed_count,
+                    due_count,
+                    completion_rate: f64::from(completed_count) / f64::from(due_count),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(HabitStats {
+            habit_id,
+            granularity,
+            buckets,
+        })
+    }
+
+    /// Habits to check in for on `day`.
+    pub fn get_due_habits(&self, day: NaiveDate) -> Result<Vec<Habit>, HabitTrackerError> {
+        let today = self.today();
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM habitEntry WHERE habitId = ?1")?;
+        let mut due_habits = vec![];
+        for habit in self.get_habits(false)? {
+            let entries = statement
+                .query_map(params![habit.id], HabitEntry::from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            if !self.due_days(&habit, &entries, day, day, today)?.is_empty() {
+                due_habits.push(habit);
+            }
+        }
+        Ok(due_habits)
+    }
+
+    /// Days within `range` on which the habit is due, see `due_days`.
+    pub fn get_due_dates(
+        &self,
+        habit_id: i64,
+        range: DateRange,
+    ) -> Result<Vec<NaiveDate>, HabitTrackerError> {
+        validate_date_range(&range)?;
+        let habit = self
+            .conn
+            .query_row(
+                "SELECT * FROM habit WHERE id = ?1",
+                params![habit_id],
+                Habit::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| habit_not_found(habit_id))?;
+        let entries = self
+            .conn
+            .prepare("SELECT * FROM habitEntry WHERE habitId = ?1")?
+            .query_map(params![habit_id], HabitEntry::from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        self.due_days(&habit, &entries, range.start, range.end, self.today())
+    }
+
+    /// Days between `start` and `end` (inclusive) on which `habit` is due, given its entries.
+    /// Daily habits follow their schedule, Apple Calendar habits are due on the days any of
+    /// their linked events occur. Linked events that were removed from the calendar no
+    /// longer occur.
+    fn due_days(
+        &self,
+        habit: &Habit,
+        entries: &[HabitEntry],
+        start: NaiveDate,
+        end: NaiveDate,
+        today: NaiveDate,
+    ) -> Result<Vec<NaiveDate>, HabitTrackerError> {
+        if habit.habit_type == HabitType::Daily {
+            return Ok(scheduled_days(
+                habit,
+                entries,
+                start,
+                end,
+                today,
+                self.week_start,
+            ));
+        }
+        // with the occurrences moved out of them, see `AppleCalendarEvent::parent_id`
+        let mut statement = self.conn.prepare_cached(
+            "SELECT * FROM appleCalendarEvent WHERE (id = ?1 OR parentId = ?1) AND removedAt IS NULL",
+        )?;
+        let mut days = vec![];
+        for event_id in &habit.event_ids.values {
+            let events = statement
+                .query_map(params![event_id], AppleCalendarEvent::from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            for event in events {
+                days.extend(event.occurrence_days(start, end, |date| self.calendar_day(date)));
+            }
+        }
+        days.sort();
+        days.dedup();
+        Ok(days)
+    }
+
+    pub fn get_habits(&self, include_archived: bool) -> Result<Vec<Habit>, HabitTrackerError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM habit WHERE ?1 OR archivedAt IS NULL")?;
+        let habit_iter = statement.query_map(params![include_archived], Habit::from_row)?;
+        Ok(habit_iter.collect::<Result<Vec<_>>>()?)
+    }
+
+    pub fn update_habit(&self, request: UpdateHabitRequest) -> Result<Habit, HabitTrackerError> {
+        validate_update_habit(&self.conn, &request)?;
+        let transaction = self.conn.unchecked_transaction()?;
+        transaction.execute(
+            "UPDATE habit SET habitType = ?1, eventIds = ?2, title = ?3, question = ?4, kind = ?5, unit = ?6, target = ?7, schedule = ?8, removedEventIds = NULL WHERE id = ?9",
             params![
                 request.habit_type,
                 request.event_ids,
                 request.title,
                 request.question,
+                request.kind,
+                request.unit,
+                request.target,
+                request.schedule,
                 request.id
             ],
         )?;
-        self.conn.query_row(
-            "SELECT * FROM habit WHERE id = ?1",
-            params![request.id],
-            Habit::from_row,
-        )
+        let habit = transaction
+            .query_row(
+                "SELECT * FROM habit WHERE id = ?1",
+                params![request.id],
+                Habit::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| habit_not_found(request.id))?;
+        if habit.kind == HabitKind::Measurable {
+            // keep derived completion in line with a changed target
+            transaction.execute(
+                "UPDATE habitEntry SET completed = COALESCE(value >= ?1, 0) WHERE habitId = ?2",
+                params![habit.target, habit.id],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(habit)
+    }
+
+    pub fn archive_habit(&self, id: i64) -> Result<Habit, HabitTrackerError> {
+        self.set_habit_archived_at(id, Some(Utc::now()))
+    }
+
+    pub fn unarchive_habit(&self, id: i64) -> Result<Habit, HabitTrackerError> {
+        self.set_habit_archived_at(id, None)
+    }
+
+    fn set_habit_archived_at(
+        &self,
+        id: i64,
+        archived_at: Option<DateTime<Utc>>,
+    ) -> Result<Habit, HabitTrackerError> {
+        self.conn.execute(
+            "UPDATE habit SET archivedAt = ?1 WHERE id = ?2",
+            params![archived_at, id],
+        )?;
+        self.conn
+            .query_row(
+                "SELECT * FROM habit WHERE id = ?1",
+                params![id],
+                Habit::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| habit_not_found(id))
+    }
+
+    pub fn delete_habit(&mut self, id: i64) -> Result<(), HabitTrackerError> {
+        let transaction = self.conn.transaction()?;
+        // entries are removed explicitly as habitEntry.habitId has no ON DELETE CASCADE
+        transaction.execute("DELETE FROM habitEntry WHERE habitId = ?1", params![id])?;
+        let deleted = transaction.execute("DELETE FROM habit WHERE id = ?1", params![id])?;
+        if deleted == 0 {
+            return Err(habit_not_found(id));
+        }
+        Ok(transaction.commit()?)
     }
 
     pub fn insert_habit_entries(
         &mut self,
         request: InsertHabitEntriesRequest,
-    ) -> Result<Vec<HabitEntry>> {
+    ) -> Result<Vec<HabitEntry>, HabitTrackerError> {
+        validate_insert_habit_entries(&self.conn, &request)?;
         let transaction = self.conn.transaction()?;
+        let mut ids = Vec::with_capacity(request.data.len());
         {
-            let mut statement = transaction
-                .prepare("INSERT INTO habitEntry (completed, habitId) VALUES (?1, ?2)")?;
+            // a second check-in on the same day replaces the first
+            let mut statement = transaction.prepare(
+                "INSERT INTO habitEntry (completed, habitId, date, day, value, timezone) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT(habitId, day) DO UPDATE SET completed = excluded.completed, date = excluded.date, value = excluded.value, timezone = excluded.timezone",
+            )?;
+            let mut id_statement =
+                transaction.prepare("SELECT id FROM habitEntry WHERE habitId = ?1 AND day = ?2")?;
+            let mut habit_statement = transaction.prepare("SELECT * FROM habit WHERE id = ?1")?;
             for InsertHabitEntryItem {
                 completed,
                 habit_id,
+                date,
+                value,
             } in &request.data
             {
-                statement.execute(params![completed, habit_id])?;
+                let habit = habit_statement
+                    .query_row(params![habit_id], Habit::from_row)
+                    .optional()?
+                    .ok_or_else(|| habit_not_found(*habit_id))?;
+                let completed = habit.is_completed_by(*completed, *value);
+                let date = date.unwrap_or_else(Utc::now);
+                let day = local_day(date, self.timezone, self.day_rollover_hour);
+                statement.execute(params![
+                    completed,
+                    habit_id,
+                    date,
+                    day,
+                    value,
+                    self.timezone.name()
+                ])?;
+                ids.push(id_statement.query_row(params![habit_id, day], |row| row.get(0))?);
             }
         }
         transaction.commit()?;
-        let mut statement = self.conn.prepare(
-            "SELECT * FROM (SELECT * FROM habitEntry ORDER BY id DESC LIMIT ?1) ORDER BY id ASC",
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM habitEntry WHERE id = ?1")?;
+        let habit_entries = ids
+            .into_iter()
+            .map(|id: i64| statement.query_row(params![id], HabitEntry::from_row))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(habit_entries)
+    }
+
+    pub fn update_habit_entry(
+        &self,
+        request: UpdateHabitEntryRequest,
+    ) -> Result<HabitEntry, HabitTrackerError> {
+        validate_update_habit_entry(&request)?;
+        let habit = self
+            .conn
+            .query_row(
+                "SELECT habit.* FROM habit JOIN habitEntry ON habitEntry.habitId = habit.id WHERE habitEntry.id = ?1",
+                params![request.id],
+                Habit::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| habit_entry_not_found(request.id))?;
+        self.conn.execute(
+            "UPDATE habitEntry SET completed = ?1, date = ?2, day = ?3, value = ?4, timezone = ?5 WHERE id = ?6",
+            params![
+                habit.is_completed_by(request.completed, request.value),
+                request.date,
+                self.local_day(request.date),
+                request.value,
+                self.timezone.name(),
+                request.id
+            ],
         )?;
-        let habit_entry_iter =
-            statement.query_map(params![request.data.len()], HabitEntry::from_row)?;
-        habit_entry_iter.collect::<Result<Vec<_>>>()
+        Ok(self.conn.query_row(
+            "SELECT * FROM habitEntry WHERE id = ?1",
+            params![request.id],
+            HabitEntry::from_row,
+        )?)
     }
 
-    #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-    pub fn get_apple_calendar_events(&self) -> Result<Vec<AppleCalendarEvent>> {
-        let mut statement = self.conn.prepare("SELECT * FROM appleCalendarEvent")?;
+    pub fn delete_habit_entry(&self, id: i64) -> Result<(), HabitTrackerError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM habitEntry WHERE id = ?1", params![id])?;
+        if deleted == 0 {
+            return Err(habit_entry_not_found(id));
+        }
+        Ok(())
+    }
+
+    /// Events currently in the calendar, without tombstones.
+    pub fn get_apple_calendar_events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+        let mut statement = self.conn.prepare(
+            "SELECT * FROM appleCalendarEvent WHERE removedAt IS NULL AND parentId IS NULL",
+        )?;
         let apple_calendar_event_iter = statement.query_map([], AppleCalendarEvent::from_row)?;
-        apple_calendar_event_iter.collect::<Result<Vec<_>>>()
+        Ok(apple_calendar_event_iter.collect::<Result<Vec<_>>>()?)
     }
 
-    #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-    pub fn reset_apple_calendar_events(
+    /// Brings the stored events in line with `events`, every recurring event of the
+    /// calendar. New and changed events are upserted, stored events missing from `events`
+    /// are tombstoned and habits linked to them flagged, see `Habit::removed_event_ids`.
+    pub fn sync_calendar_events(
         &mut self,
-        request: Vec<AppleCalendarEvent>,
-    ) -> Result<Vec<AppleCalendarEvent>> {
+        events: Vec<AppleCalendarEvent>,
+    ) -> Result<CalendarSyncReport, HabitTrackerError> {
         let transaction = self.conn.transaction()?;
-        // squash and replace all events
-        transaction.execute("DELETE FROM appleCalendarEvent", [])?;
-        {
-            let mut statement = transaction.prepare(
-                "INSERT INTO appleCalendarEvent (id, name, startDate, recurrence) VALUES (?1, ?2, ?3, ?4)",
+        let stored = transaction
+            .prepare("SELECT * FROM appleCalendarEvent")?
+            .query_map([], AppleCalendarEvent::from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        let stored_by_id = stored
+            .iter()
+            .map(|event| (event.id.as_str(), event))
+            .collect::<HashMap<_, _>>();
+        let mut report = CalendarSyncReport::default();
+        let mut synced_ids = HashSet::new();
+        let mut restored_ids = HashSet::new();
+        for event in &events {
+            if !synced_ids.insert(event.id.as_str()) {
+                return Err(HabitTrackerError::Conflict {
+                    message: format!("Calendar event {} is listed more than once", event.id),
+                });
+            }
+            let added = match stored_by_id.get(event.id.as_str()) {
+                None => true,
+                Some(stored) if stored.removed_at.is_some() => {
+                    restored_ids.insert(event.id.as_str());
+                    true
+                }
+                Some(stored)
+                    if stored.name == event.name
+                        && stored.start_date == event.start_date
+                        && stored.recurrence == event.recurrence
+                        && stored.excluded_dates == event.excluded_dates
+                        && stored.parent_id == event.parent_id =>
+                {
+                    continue
+                }
+                Some(_) => false,
+            };
+            event.upsert(&transaction)?;
+            let event = transaction.query_row(
+                "SELECT * FROM appleCalendarEvent WHERE id = ?1",
+                params![event.id],
+                AppleCalendarEvent::from_row,
             )?;
-            for AppleCalendarEvent {
-                id,
-                name,
-                start_date,
-                recurrence,
-            } in &request
-            {
-                statement.execute(params![id, name, start_date, recurrence])?;
+            if added {
+                report.added.push(event);
+            } else {
+                report.updated.push(event);
+            }
+        }
+        let removed_at = Utc::now();
+        for event in stored {
+            if event.removed_at.is_none() && !synced_ids.contains(event.id.as_str()) {
+                transaction.execute(
+                    "UPDATE appleCalendarEvent SET removedAt = ?1 WHERE id = ?2",
+                    params![removed_at, event.id],
+                )?;
+                report.removed.push(AppleCalendarEvent {
+                    removed_at: Some(removed_at),
+                    ..event
+                });
+            }
+        }
+
+        let removed_ids = report
+            .removed
+            .iter()
+            .map(|event| event.id.as_str())
+            .collect::<HashSet<_>>();
+        let habits = transaction
+            .prepare("SELECT * FROM habit")?
+            .query_map([], Habit::from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        for mut habit in habits {
+            let flagged_ids = &mut habit.removed_event_ids.values;
+            let flagged_count = flagged_ids.len();
+            // events that came back no longer need relinking
+            flagged_ids.retain(|id| !restored_ids.contains(id.as_str()));
+            let restored = flagged_ids.len() < flagged_count;
+            let newly_removed = habit
+                .event_ids
+                .values
+                .iter()
+                .filter(|id| removed_ids.contains(id.as_str()) && !flagged_ids.contains(id))
+                .cloned()
+                .collect::<Vec<_>>();
+            if newly_removed.is_empty() && !restored {
+                continue;
+            }
+            flagged_ids.extend_from_slice(&newly_removed);
+            transaction.execute(
+                "UPDATE habit SET removedEventIds = ?1 WHERE id = ?2",
+                params![habit.removed_event_ids, habit.id],
+            )?;
+            if !newly_removed.is_empty() {
+                report.flagged_habits.push(habit);
             }
         }
         transaction.commit()?;
-        let mut statement = self.conn.prepare("SELECT * FROM appleCalendarEvent")?;
-        let apple_calendar_events_iter = statement.query_map([], AppleCalendarEvent::from_row)?;
-        apple_calendar_events_iter.collect::<Result<Vec<_>>>()
+        Ok(report)
     }
 }
 
 #[cfg(test)]
 pub mod unit_tests {
     use crate::api::{
-        AppleCalendarEvent, EventIds, Habit, HabitEntry, HabitTrackerService, HabitType,
+        compute_streak, local_day, AppleCalendarEvent, DayOfWeek, EventIds, ExcludedDates, Habit,
+        HabitEntry, HabitKind, HabitStreak, HabitTrackerService, HabitType, Schedule,
+        StatsGranularity,
     };
     use crate::get_test_app_config;
-    use chrono::Utc;
+    use chrono::{DateTime, NaiveDate, Utc};
+    use chrono_tz::Tz;
     use rusqlite::types::{FromSql, FromSqlError, ToSqlOutput, Value, ValueRef};
     use rusqlite::{params, Connection, Result, ToSql};
+    use std::collections::HashSet;
     use std::error::Error;
 
     #[test]
@@ -313,6 +1214,27 @@ pub mod unit_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_habit_kinds() -> Result<()> {
+        // column_result method
+        assert_eq!(HabitKind::column_result("yesNo".into())?, HabitKind::YesNo);
+        assert_eq!(
+            HabitKind::column_result("measurable".into())?,
+            HabitKind::Measurable
+        );
+        assert_eq!(
+            HabitKind::column_result("incorrect_kind".into()).unwrap_err(),
+            FromSqlError::InvalidType,
+        );
+        // to_sql method
+        assert_eq!(HabitKind::YesNo.to_sql()?, ToSqlOutput::from("yesNo"));
+        assert_eq!(
+            HabitKind::Measurable.to_sql()?,
+            ToSqlOutput::from("measurable"),
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_event_ids() -> Result<()> {
         // column result method
@@ -344,6 +1266,231 @@ pub mod unit_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_compute_streak() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 5, d).unwrap();
+        // due on the 1st to 3rd and 5th to 9th, the 4th is not a due day
+        let due_days = [1, 2, 3, 5, 6, 7, 8, 9].map(day);
+        let completed_days = HashSet::from([1, 2, 3, 5, 7, 8].map(day));
+
+        assert_eq!(
+            compute_streak(1, &due_days, &completed_days, day(9)),
+            HabitStreak {
+                habit_id: 1,
+                current_streak: 2,
+                longest_streak: 4,
+                last_break_date: Some(day(6)),
+            }
+        );
+        // today is still pending and does not break the streak
+        assert_eq!(
+            compute_streak(1, &due_days, &completed_days, day(8)),
+            HabitStreak {
+                habit_id: 1,
+                current_streak: 2,
+                longest_streak: 4,
+                last_break_date: Some(day(6)),
+            }
+        );
+        assert_eq!(
+            compute_streak(1, &due_days, &completed_days, day(5)),
+            HabitStreak {
+                habit_id: 1,
+                current_streak: 4,
+                longest_streak: 4,
+                last_break_date: None,
+            }
+        );
+        assert_eq!(
+            compute_streak(1, &[], &HashSet::new(), day(5)),
+            HabitStreak {
+                habit_id: 1,
+                current_streak: 0,
+                longest_streak: 0,
+                last_break_date: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_schedule() -> Result<()> {
+        // column_result method
+        assert_eq!(Schedule::column_result(ValueRef::Null)?, Schedule::EveryDay);
+        assert_eq!(
+            Schedule::column_result("{\"TimesPerWeek\":{\"count\":3}}".into())?,
+            Schedule::TimesPerWeek { count: 3 }
+        );
+        assert_eq!(
+            Schedule::column_result("invalid json".into()).unwrap_err(),
+            FromSqlError::InvalidType
+        );
+        // to_sql method
+        assert_eq!(
+            Schedule::Weekdays {
+                days: vec![DayOfWeek::Monday]
+            }
+            .to_sql()?,
+            ToSqlOutput::from("{\"Weekdays\":{\"days\":[\"Monday\"]}}".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedule_is_due() {
+        // 2025-06-02 is a Monday
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 6, d).unwrap();
+        let due_in_first_week = |schedule: Schedule, completed: &[u32], today: u32| {
+            let completed_days = completed.iter().map(|d| day(*d)).collect::<HashSet<_>>();
+            (2..=8)
+                .filter(|d| {
+                    schedule.is_due(day(*d), &completed_days, day(today), DayOfWeek::Monday)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            due_in_first_week(Schedule::EveryDay, &[], 2),
+            vec![2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(
+            due_in_first_week(
+                Schedule::Weekdays {
+                    days: vec![DayOfWeek::Monday, DayOfWeek::Friday]
+                },
+                &[],
+                2
+            ),
+            vec![2, 6]
+        );
+        assert_eq!(
+            due_in_first_week(
+                Schedule::EveryNDays {
+                    interval: 3,
+                    start: day(3)
+                },
+                &[],
+                2
+            ),
+            vec![3, 6]
+        );
+        // upcoming days are due until the quota is met
+        assert_eq!(
+            due_in_first_week(Schedule::TimesPerWeek { count: 2 }, &[3], 3),
+            vec![3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(
+            due_in_first_week(Schedule::TimesPerWeek { count: 2 }, &[3, 5], 5),
+            vec![3, 5]
+        );
+        // a skipped day is only due once the quota can no longer be met without it
+        assert_eq!(
+            due_in_first_week(Schedule::TimesPerWeek { count: 2 }, &[3], 9),
+            vec![3, 8]
+        );
+        assert_eq!(
+            due_in_first_week(Schedule::TimesPerWeek { count: 3 }, &[], 9),
+            vec![6, 7, 8]
+        );
+        assert_eq!(
+            due_in_first_week(Schedule::TimesPerMonth { count: 1 }, &[], 9),
+            Vec::<u32>::new()
+        );
+        // with weeks starting on Sunday, the 8th starts a new week with a new quota
+        let completed_days = HashSet::from([day(3), day(5)]);
+        assert_eq!(
+            (2..=8)
+                .filter(|d| Schedule::TimesPerWeek { count: 2 }.is_due(
+                    day(*d),
+                    &completed_days,
+                    day(5),
+                    DayOfWeek::Sunday
+                ))
+                .collect::<Vec<_>>(),
+            vec![3, 5, 8]
+        );
+    }
+
+    #[test]
+    fn test_stats_granularity_period() {
+        let date = |y: i32, m: u32, d: u32| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        // 2025-01-01 falls in the first ISO week of 2025, which starts in 2024
+        assert_eq!(
+            StatsGranularity::Week.period(date(2025, 1, 1), DayOfWeek::Monday),
+            ("2025-W01".to_string(), date(2024, 12, 30), date(2025, 1, 5))
+        );
+        assert_eq!(
+            StatsGranularity::Week.period(date(2021, 1, 3), DayOfWeek::Monday),
+            ("2020-W53".to_string(), date(2020, 12, 28), date(2021, 1, 3))
+        );
+        // a Sunday starts the week that is mostly ISO week 2021-W01
+        assert_eq!(
+            StatsGranularity::Week.period(date(2021, 1, 3), DayOfWeek::Sunday),
+            ("2021-W01".to_string(), date(2021, 1, 3), date(2021, 1, 9))
+        );
+        assert_eq!(
+            StatsGranularity::Month.period(date(2024, 2, 14), DayOfWeek::Monday),
+            ("2024-02".to_string(), date(2024, 2, 1), date(2024, 2, 29))
+        );
+        assert_eq!(
+            StatsGranularity::Year.period(date(2024, 2, 14), DayOfWeek::Monday),
+            ("2024".to_string(), date(2024, 1, 1), date(2024, 12, 31))
+        );
+    }
+
+    #[test]
+    fn test_local_day() {
+        let date = |y: i32, m: u32, d: u32| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let instant = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let new_york = Tz::America__New_York;
+        // 2025-03-09 02:00 EST jumps to 03:00 EDT, the day still ends at 03:00 on the wall clock
+        assert_eq!(
+            local_day(instant("2025-03-09T06:59:00Z"), new_york, 3),
+            date(2025, 3, 8)
+        );
+        assert_eq!(
+            local_day(instant("2025-03-09T07:00:00Z"), new_york, 3),
+            date(2025, 3, 9)
+        );
+        // 2025-11-02 02:00 EDT falls back to 01:00 EST, both 01:30s count towards the previous day
+        assert_eq!(
+            local_day(instant("2025-11-02T05:30:00Z"), new_york, 3),
+            date(2025, 11, 1)
+        );
+        assert_eq!(
+            local_day(instant("2025-11-02T06:30:00Z"), new_york, 3),
+            date(2025, 11, 1)
+        );
+        assert_eq!(
+            local_day(instant("2025-11-02T07:59:00Z"), new_york, 3),
+            date(2025, 11, 1)
+        );
+        assert_eq!(
+            local_day(instant("2025-11-02T08:00:00Z"), new_york, 3),
+            date(2025, 11, 2)
+        );
+        // 2025-03-30 02:00 CET jumps to 03:00 CEST
+        let paris = Tz::Europe__Paris;
+        assert_eq!(
+            local_day(instant("2025-03-29T22:59:00Z"), paris, 0),
+            date(2025, 3, 29)
+        );
+        assert_eq!(
+            local_day(instant("2025-03-29T23:00:00Z"), paris, 0),
+            date(2025, 3, 30)
+        );
+        assert_eq!(
+            local_day(instant("2025-03-30T22:00:00Z"), paris, 0),
+            date(2025, 3, 31)
+        );
+        // 00:30 at UTC+2 is still the previous day in UTC
+        let late_check_in = instant("2025-06-01T22:30:00Z");
+        assert_eq!(
+            local_day(late_check_in, Tz::Europe__Berlin, 0),
+            date(2025, 6, 2)
+        );
+        assert_eq!(local_day(late_check_in, Tz::UTC, 0), date(2025, 6, 1));
+    }
+
     fn create_habit(db_connection: &Connection) -> Result<Habit> {
         let fixture = Habit {
             id: 1,
@@ -353,6 +1500,12 @@ pub mod unit_tests {
             },
             title: "some title".into(),
             question: "some question".into(),
+            archived_at: None,
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+            removed_event_ids: EventIds::default(),
         };
         db_connection.execute(
             "INSERT INTO habit (habitType, eventIds, title, question) VALUES (?1, ?2, ?3, ?4)",
@@ -378,24 +1531,42 @@ pub mod unit_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_foreign_keys_enabled() -> Result<(), Box<dyn Error>> {
+        let app_config = get_test_app_config()?;
+        let db_connection = HabitTrackerService::build(app_config)?.conn;
+        assert!(db_connection
+            .execute(
+                "INSERT INTO habitEntry (habitId, completed, day) VALUES (?1, ?2, ?3)",
+                params![42, true, "2025-01-01"],
+            )
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_habit_entry_from_row() -> Result<(), Box<dyn Error>> {
+        let date = Utc::now();
         let habit_entry_fixture = HabitEntry {
             id: 1,
             habit_id: 1,
             completed: true,
-            date: Utc::now(),
+            date,
+            day: local_day(date, Tz::UTC, 0),
+            value: None,
+            timezone: None,
         };
         let app_config = get_test_app_config()?;
         let db_connection = HabitTrackerService::build(app_config)?.conn;
         // create habit to ensure a habit_id for entry
         create_habit(&db_connection)?;
         db_connection.execute(
-            "INSERT INTO habitEntry (habitId, completed, date) VALUES (?1, ?2, ?3)",
+            "INSERT INTO habitEntry (habitId, completed, date, day) VALUES (?1, ?2, ?3, ?4)",
             params![
                 habit_entry_fixture.habit_id,
                 habit_entry_fixture.completed,
                 habit_entry_fixture.date,
+                habit_entry_fixture.day,
             ],
         )?;
 
@@ -418,6 +1589,11 @@ pub mod unit_tests {
             name: "calendarEventName".into(),
             start_date: Utc::now(),
             recurrence: "recurrence".into(),
+            excluded_dates: ExcludedDates::default(),
+            parent_id: None,
+            recurrence_rule: None,
+            recurrence_error: None,
+            removed_at: None,
         };
         db_connection.execute(
             "INSERT INTO appleCalendarEvent (id, name, startDate, recurrence) VALUES (?1, ?2, ?3, ?4)",
@@ -1,30 +1,419 @@
-use std::error::Error;
+use crate::{
+    api::DayOfWeek,
+    backup::BackupConfig,
+    calendar_source::CalendarSourceConfig,
+    error::{FieldError, HabitTrackerError},
+    validation::validate_config_file,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
+#[derive(Clone)]
 pub struct AppConfig {
     pub db_path: String,
+    pub timezone: Tz,
+    // local hour at which a day ends, e.g. 3 to count check-ins before 03:00 towards the previous day
+    pub day_rollover_hour: u32,
+    // first day of the weeks `TimesPerWeek` quotas and weekly stats count in
+    pub week_start: DayOfWeek,
+    pub calendar_source: CalendarSourceConfig,
+    pub backups: BackupConfig,
+}
+
+/// The config file, see `config_file_path`. Every setting may be left out of the file to
+/// use its default, and most can be overridden with an environment variable.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Type)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Defaults to `habits.db` in the data directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_path: Option<String>,
+    /// IANA name, e.g. `Europe/Paris`. Defaults to the system timezone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    pub day_rollover_hour: u32,
+    pub week_start: DayOfWeek,
+    pub calendar: CalendarSettings,
+    pub reminders: ReminderSettings,
+    pub backups: BackupSettings,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        ConfigFile {
+            db_path: None,
+            timezone: None,
+            day_rollover_hour: 0,
+            week_start: DayOfWeek::Monday,
+            calendar: CalendarSettings::default(),
+            reminders: ReminderSettings::default(),
+            backups: BackupSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarSourceKind {
+    AppleScript,
+    Ics,
+    Fixtures,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Type)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarSettings {
+    pub source: CalendarSourceKind,
+    /// Apple calendars to read recurring events from.
+    pub names: Vec<String>,
+    /// The `.ics` files of an `ics` source, or the directory of a `fixtures` source.
+    pub paths: Vec<String>,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        CalendarSettings {
+            source: CalendarSourceKind::AppleScript,
+            names: vec!["Home".to_string()],
+            paths: vec![],
+        }
+    }
+}
+
+/// Local times as `HH:MM`, read by `scripts/setup-background-services.sh`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Type)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReminderSettings {
+    pub sync_time: String,
+    pub notify_times: Vec<String>,
+}
+
+impl Default for ReminderSettings {
+    fn default() -> Self {
+        ReminderSettings {
+            sync_time: "14:00".to_string(),
+            notify_times: vec!["20:00".to_string()],
+        }
+    }
+}
+
+/// Backups are taken when the app starts and before schema migrations. Only the ones
+/// taken on start rotate, see `retained_backups`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Type)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupSettings {
+    /// Defaults to `backups` next to the database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Days whose latest backup is kept.
+    pub keep_daily: u32,
+    /// Weeks whose latest backup is kept, on top of the daily ones.
+    pub keep_weekly: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            directory: None,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
 }
 
 pub fn get_app_config() -> Result<AppConfig, Box<dyn Error>> {
-    let db_path = if let Ok(custom_path) = std::env::var("HABIT_TRACKER_DB_PATH") {
-        custom_path
-    } else if cfg!(debug_assertions) {
-        // Development
-        "habits.db".to_string()
+    let mut config = read_config_file(&config_file_path()?)?;
+    apply_env_overrides(&mut config, |name| std::env::var(name).ok())?;
+    resolve_config(config)
+}
+
+/// `HABIT_TRACKER_CONFIG_PATH`, or `config.toml` in the data directory.
+pub fn config_file_path() -> Result<PathBuf, HabitTrackerError> {
+    match std::env::var_os("HABIT_TRACKER_CONFIG_PATH") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(data_dir()?.join("config.toml")),
+    }
+}
+
+fn data_dir() -> Result<PathBuf, HabitTrackerError> {
+    if cfg!(debug_assertions) {
+        // Development: the working directory
+        Ok(PathBuf::new())
     } else {
         // Production: use platform-appropriate app data directory
-        let mut path = dirs::data_dir().ok_or("Could not determine data directory")?;
+        let mut path = dirs::data_dir().ok_or_else(|| HabitTrackerError::Storage {
+            message: "Could not determine data directory".to_string(),
+        })?;
         path.push("HabitTracker");
         // Create directory if it doesn't exist
-        std::fs::create_dir_all(&path)?;
-        path.push("habits.db");
-        let path_str = path.to_string_lossy().to_string();
-        path_str
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+}
+
+/// The defaults when there is no file at `path`.
+pub fn read_config_file(path: &Path) -> Result<ConfigFile, HabitTrackerError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+        Err(e) => return Err(e.into()),
     };
-    Ok(AppConfig { db_path })
+    let config = toml::from_str(&content).map_err(|e| {
+        HabitTrackerError::validation(vec![FieldError {
+            field: path.display().to_string(),
+            message: e.message().to_string(),
+        }])
+    })?;
+    validate_config_file(&config)?;
+    Ok(config)
+}
+
+/// Validates and writes `config`, which takes effect on the next start.
+pub fn write_config_file(path: &Path, config: &ConfigFile) -> Result<(), HabitTrackerError> {
+    validate_config_file(config)?;
+    let content = toml::to_string_pretty(config).map_err(|e| HabitTrackerError::Storage {
+        message: e.to_string(),
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Environment variables win over the file. `env` looks a variable up, `std::env::var`
+/// outside of tests.
+pub fn apply_env_overrides(
+    config: &mut ConfigFile,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(db_path) = env("HABIT_TRACKER_DB_PATH") {
+        config.db_path = Some(db_path);
+    }
+    if let Some(timezone) = env("HABIT_TRACKER_TIMEZONE") {
+        config.timezone = Some(timezone);
+    }
+    if let Some(hour) = env("HABIT_TRACKER_DAY_ROLLOVER_HOUR") {
+        config.day_rollover_hour = hour
+            .parse()
+            .map_err(|_| format!("Invalid HABIT_TRACKER_DAY_ROLLOVER_HOUR: {hour}"))?;
+    }
+    if let Some(day) = env("HABIT_TRACKER_WEEK_START") {
+        config.week_start = [
+            DayOfWeek::Monday,
+            DayOfWeek::Tuesday,
+            DayOfWeek::Wednesday,
+            DayOfWeek::Thursday,
+            DayOfWeek::Friday,
+            DayOfWeek::Saturday,
+            DayOfWeek::Sunday,
+        ]
+        .into_iter()
+        .find(|week_start| format!("{week_start:?}").eq_ignore_ascii_case(&day))
+        .ok_or_else(|| format!("Invalid HABIT_TRACKER_WEEK_START: {day}"))?;
+    }
+    if let Some(source) = env("HABIT_TRACKER_CALENDAR_SOURCE") {
+        config.calendar.source = match source.as_str() {
+            "applescript" => CalendarSourceKind::AppleScript,
+            "ics" => CalendarSourceKind::Ics,
+            "fixtures" => CalendarSourceKind::Fixtures,
+            _ => {
+                return Err(format!(
+                    "Unknown calendar source: {source}, expected applescript, ics or fixtures"
+                )
+                .into())
+            }
+        };
+    }
+    // a list like PATH, `:` separated (`;` on Windows)
+    if let Some(paths) = env("HABIT_TRACKER_CALENDAR_PATHS") {
+        config.calendar.paths = std::env::split_paths(&paths)
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+    }
+    if let Some(names) = env("HABIT_TRACKER_CALENDAR_NAMES") {
+        config.calendar.names = names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect();
+    }
+    Ok(())
+}
+
+fn resolve_config(config: ConfigFile) -> Result<AppConfig, Box<dyn Error>> {
+    validate_config_file(&config)?;
+    let db_path = match config.db_path {
+        Some(db_path) => db_path,
+        None if cfg!(debug_assertions) => "habits.db".to_string(),
+        None => data_dir()?.join("habits.db").to_string_lossy().into_owned(),
+    };
+    let timezone = match config.timezone {
+        Some(timezone) => timezone,
+        None => iana_time_zone::get_timezone()?,
+    };
+    let timezone = timezone
+        .parse::<Tz>()
+        .map_err(|_| format!("Unknown timezone: {timezone}"))?;
+    let mut paths = config.calendar.paths.into_iter().map(PathBuf::from);
+    let calendar_source = match config.calendar.source {
+        CalendarSourceKind::AppleScript => CalendarSourceConfig::AppleScript {
+            calendar_names: config.calendar.names,
+        },
+        CalendarSourceKind::Ics => CalendarSourceConfig::Ics {
+            paths: paths.collect(),
+        },
+        // validation ensures a single path
+        CalendarSourceKind::Fixtures => CalendarSourceConfig::Fixtures {
+            directory: paths.next().unwrap_or_default(),
+        },
+    };
+    let backups = BackupConfig {
+        directory: match config.backups.directory {
+            Some(directory) => PathBuf::from(directory),
+            None => Path::new(&db_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join("backups"),
+        },
+        keep_daily: config.backups.keep_daily,
+        keep_weekly: config.backups.keep_weekly,
+    };
+    Ok(AppConfig {
+        db_path,
+        timezone,
+        day_rollover_hour: config.day_rollover_hour,
+        week_start: config.week_start,
+        calendar_source,
+        backups,
+    })
 }
 
 pub fn get_test_app_config() -> Result<AppConfig, Box<dyn Error>> {
     Ok(AppConfig {
         db_path: ":memory:".to_string(),
+        timezone: Tz::UTC,
+        day_rollover_hour: 0,
+        week_start: DayOfWeek::Monday,
+        calendar_source: CalendarSourceConfig::Fixtures {
+            directory: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/calendar_events"),
+        },
+        // in-memory databases are never backed up
+        backups: BackupConfig {
+            directory: std::env::temp_dir().join("habit-tracker-backups"),
+            keep_daily: 7,
+            keep_weekly: 4,
+        },
     })
 }
+
+#[cfg(test)]
+pub mod unit_tests {
+    use super::{apply_env_overrides, resolve_config, CalendarSourceKind, ConfigFile};
+    use crate::{api::DayOfWeek, calendar_source::CalendarSourceConfig};
+    use chrono_tz::Tz;
+    use std::{collections::HashMap, error::Error, path::PathBuf};
+
+    #[test]
+    fn test_parse_config_file() -> Result<(), Box<dyn Error>> {
+        assert_eq!(toml::from_str::<ConfigFile>("")?, ConfigFile::default());
+
+        let config: ConfigFile = toml::from_str(
+            r#"
+            timezone = "Europe/Paris"
+            week_start = "Sunday"
+
+            [calendar]
+            names = ["Home", "Sport"]
+
+            [reminders]
+            notify_times = ["08:30", "20:00"]
+            "#,
+        )?;
+        assert_eq!(config.timezone.as_deref(), Some("Europe/Paris"));
+        assert_eq!(config.week_start, DayOfWeek::Sunday);
+        assert_eq!(config.calendar.source, CalendarSourceKind::AppleScript);
+        assert_eq!(config.calendar.names, vec!["Home", "Sport"]);
+        assert_eq!(config.reminders.sync_time, "14:00");
+        assert_eq!(config.reminders.notify_times, vec!["08:30", "20:00"]);
+
+        // typos are rejected rather than silently ignored
+        assert!(toml::from_str::<ConfigFile>("day_rollover = 3").is_err());
+        assert!(toml::from_str::<ConfigFile>("[calendar]\nsource = \"google\"").is_err());
+
+        // what is written reads back the same
+        assert_eq!(
+            toml::from_str::<ConfigFile>(&toml::to_string_pretty(&config)?)?,
+            config
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_overrides() -> Result<(), Box<dyn Error>> {
+        let env = HashMap::from([
+            ("HABIT_TRACKER_DB_PATH", "/tmp/habits.db"),
+            ("HABIT_TRACKER_TIMEZONE", "Asia/Tokyo"),
+            ("HABIT_TRACKER_DAY_ROLLOVER_HOUR", "3"),
+            ("HABIT_TRACKER_WEEK_START", "sunday"),
+            ("HABIT_TRACKER_CALENDAR_SOURCE", "fixtures"),
+            (
+                "HABIT_TRACKER_CALENDAR_PATHS",
+                "tests/fixtures/calendar_events",
+            ),
+        ]);
+        let mut config = ConfigFile {
+            timezone: Some("Europe/Paris".to_string()),
+            ..Default::default()
+        };
+        apply_env_overrides(&mut config, |name| {
+            env.get(name).map(|value| value.to_string())
+        })?;
+        let app_config = resolve_config(config)?;
+        assert_eq!(app_config.db_path, "/tmp/habits.db");
+        assert_eq!(app_config.timezone, Tz::Asia__Tokyo);
+        assert_eq!(app_config.day_rollover_hour, 3);
+        assert_eq!(app_config.week_start, DayOfWeek::Sunday);
+        assert_eq!(
+            app_config.calendar_source,
+            CalendarSourceConfig::Fixtures {
+                directory: PathBuf::from("tests/fixtures/calendar_events")
+            }
+        );
+
+        let invalid = |name: &str, value: &str| {
+            let mut config = ConfigFile::default();
+            apply_env_overrides(&mut config, |n| (n == name).then(|| value.to_string()))
+                .and_then(|_| resolve_config(config).map(|_| ()))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            invalid("HABIT_TRACKER_DAY_ROLLOVER_HOUR", "3am"),
+            "Invalid HABIT_TRACKER_DAY_ROLLOVER_HOUR: 3am"
+        );
+        assert_eq!(
+            invalid("HABIT_TRACKER_DAY_ROLLOVER_HOUR", "24"),
+            "day_rollover_hour: Must be between 0 and 23"
+        );
+        assert_eq!(
+            invalid("HABIT_TRACKER_WEEK_START", "Sun"),
+            "Invalid HABIT_TRACKER_WEEK_START: Sun"
+        );
+        assert_eq!(
+            invalid("HABIT_TRACKER_CALENDAR_SOURCE", "ics"),
+            "calendar.paths: Required"
+        );
+        assert_eq!(
+            invalid("HABIT_TRACKER_CALENDAR_NAMES", "Home,"),
+            "calendar.names[1]: Required"
+        );
+        Ok(())
+    }
+}
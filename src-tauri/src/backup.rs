@@ -0,0 +1,302 @@
+use crate::{
+    api::{derive_recurrence_rules, DayOfWeek, HabitTrackerService, StatsGranularity},
+    error::HabitTrackerError,
+    migrations::migrate,
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{backup::Progress, Connection};
+use serde::Serialize;
+use specta::Type;
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Where database snapshots go and how many of them are kept, see `retained_backups`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupConfig {
+    pub directory: PathBuf,
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Type)]
+pub enum BackupReason {
+    Start,
+    /// Taken before upgrading the schema.
+    PreMigration,
+    /// Taken before restoring another backup, so restores can be undone.
+    PreRestore,
+    /// Taken before the doctor fixes issues.
+    PreRepair,
+    Manual,
+}
+
+impl BackupReason {
+    const ALL: [BackupReason; 5] = [
+        BackupReason::Start,
+        BackupReason::PreMigration,
+        BackupReason::PreRestore,
+        BackupReason::PreRepair,
+        BackupReason::Manual,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            BackupReason::Start => "start",
+            BackupReason::PreMigration => "pre-migration",
+            BackupReason::PreRestore => "pre-restore",
+            BackupReason::PreRepair => "pre-repair",
+            BackupReason::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+pub struct BackupInfo {
+    /// File name in the backup directory, e.g. `habits-20250601T183000.250Z-start.db`.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub reason: BackupReason,
+    pub size: u64,
+}
+
+// backups are named after the database, so several databases can share a directory
+fn backup_prefix(db_path: &Path) -> String {
+    let stem = db_path
+        .file_stem()
+        .map_or("habits".into(), |stem| stem.to_string_lossy());
+    format!("{stem}-")
+}
+
+/// Copies the database behind `conn` into the backup directory with SQLite's online
+/// backup API, which is safe while the database is in use.
+pub fn snapshot(
+    conn: &Connection,
+    db_path: &Path,
+    config: &BackupConfig,
+    reason: BackupReason,
+) -> Result<BackupInfo, HabitTrackerError> {
+    // after the newest backup, so names stay unique and ordered within a millisecond
+    let created_at = match read_backups(db_path, config)?.first() {
+        Some(newest) => Utc::now().max(newest.created_at + Duration::milliseconds(1)),
+        None => Utc::now(),
+    };
+    let name = format!(
+        "{}{}-{}.db",
+        backup_prefix(db_path),
+        created_at.format(TIMESTAMP_FORMAT),
+        reason.label()
+    );
+    fs::create_dir_all(&config.directory)?;
+    // written under another name first, so a backup cut short is never listed
+    let partial_path = config.directory.join(format!("{name}.partial"));
+    conn.backup("main", &partial_path, None)?;
+    let path = config.directory.join(&name);
+    fs::rename(&partial_path, &path)?;
+    Ok(BackupInfo {
+        name,
+        created_at,
+        reason,
+        size: fs::metadata(&path)?.len(),
+    })
+}
+
+/// Backups of the database at `db_path`, newest first.
+pub fn read_backups(
+    db_path: &Path,
+    config: &BackupConfig,
+) -> Result<Vec<BackupInfo>, HabitTrackerError> {
+    let entries = match fs::read_dir(&config.directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let prefix = backup_prefix(db_path);
+    let mut backups = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some((timestamp, label)) = name
+            .strip_prefix(&prefix)
+            .and_then(|name| name.strip_suffix(".db"))
+            .and_then(|name| name.split_once('-'))
+        else {
+            continue;
+        };
+        let (Ok(created_at), Some(reason)) = (
+            NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT),
+            BackupReason::ALL
+                .into_iter()
+                .find(|reason| reason.label() == label),
+        ) else {
+            continue;
+        };
+        backups.push(BackupInfo {
+            created_at: created_at.and_utc(),
+            reason,
+            size: entry.metadata()?.len(),
+            name,
+        });
+    }
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Whether to keep each of `backups`, newest first. Only backups taken on start rotate:
+/// the newest of each of the `keep_daily` latest days with one is kept, and of each of
+/// the `keep_weekly` latest weeks with one. The others were taken for a reason and are
+/// kept until deleted by hand. `day` is the local day of a backup.
+pub fn retained_backups(
+    backups: &[BackupInfo],
+    config: &BackupConfig,
+    day: impl Fn(DateTime<Utc>) -> NaiveDate,
+    week_start: DayOfWeek,
+) -> Vec<bool> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    backups
+        .iter()
+        .map(|backup| {
+            if backup.reason != BackupReason::Start {
+                return true;
+            }
+            let day = day(backup.created_at);
+            let (_, week, _) = StatsGranularity::Week.period(day, week_start);
+            let newest_of_day = days.len() < config.keep_daily as usize && days.insert(day);
+            let newest_of_week = weeks.len() < config.keep_weekly as usize && weeks.insert(week);
+            newest_of_day || newest_of_week
+        })
+        .collect()
+}
+
+fn backup_target(
+    service: &HabitTrackerService,
+) -> Result<(&Path, &BackupConfig), HabitTrackerError> {
+    match &service.db_path {
+        Some(db_path) => Ok((db_path, &service.backup_config)),
+        None => Err(HabitTrackerError::Storage {
+            message: "In-memory databases have no backups".to_string(),
+        }),
+    }
+}
+
+/// Backs the database up, then prunes backups, see `retained_backups`.
+pub fn create_backup(
+    service: &HabitTrackerService,
+    reason: BackupReason,
+) -> Result<BackupInfo, HabitTrackerError> {
+    let (db_path, config) = backup_target(service)?;
+    let backup = snapshot(&service.conn, db_path, config, reason)?;
+    prune_backups(service)?;
+    Ok(backup)
+}
+
+fn prune_backups(service: &HabitTrackerService) -> Result<(), HabitTrackerError> {
+    let (db_path, config) = backup_target(service)?;
+    let backups = read_backups(db_path, config)?;
+    let retained = retained_backups(
+        &backups,
+        config,
+        |date| service.local_day(date),
+        service.week_start,
+    );
+    for (backup, retained) in backups.iter().zip(retained) {
+        if !retained {
+            fs::remove_file(config.directory.join(&backup.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Backups of the database, newest first. In-memory databases have none.
+pub fn list_backups(service: &HabitTrackerService) -> Result<Vec<BackupInfo>, HabitTrackerError> {
+    match &service.db_path {
+        Some(db_path) => read_backups(db_path, &service.backup_config),
+        None => Ok(vec![]),
+    }
+}
+
+/// Replaces the database with the backup named `name`, after backing it up. Returns
+/// that backup, to undo the restore with.
+pub fn restore_backup(
+    service: &mut HabitTrackerService,
+    name: &str,
+) -> Result<BackupInfo, HabitTrackerError> {
+    if !list_backups(service)?
+        .iter()
+        .any(|backup| backup.name == name)
+    {
+        return Err(HabitTrackerError::NotFound {
+            message: format!("backup {name} not found"),
+        });
+    }
+    let (db_path, config) = backup_target(service)?;
+    // pruned only once restored, which could otherwise delete the backup to restore
+    let current = snapshot(&service.conn, db_path, config, BackupReason::PreRestore)?;
+    let path = config.directory.join(name);
+    service.conn.restore("main", path, None::<fn(Progress)>)?;
+    // the backup may predate schema changes, see `HabitTrackerService::build`
+    migrate(&mut service.conn)?;
+    derive_recurrence_rules(&service.conn)?;
+    prune_backups(service)?;
+    Ok(current)
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use super::{retained_backups, BackupConfig, BackupInfo, BackupReason};
+    use crate::api::DayOfWeek;
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_retained_backups() {
+        // 2025-06-02 is a Monday
+        let backup = |day: u32, hour: u32, reason: BackupReason| BackupInfo {
+            name: String::new(),
+            created_at: Utc.with_ymd_and_hms(2025, 6, day, hour, 0, 0).unwrap(),
+            reason,
+            size: 0,
+        };
+        let backups = [
+            backup(16, 20, BackupReason::Start),
+            backup(16, 8, BackupReason::Start),
+            backup(15, 9, BackupReason::Start),
+            backup(10, 9, BackupReason::Manual),
+            backup(9, 9, BackupReason::PreMigration),
+            backup(9, 8, BackupReason::Start),
+            backup(3, 9, BackupReason::Start),
+            backup(2, 9, BackupReason::Start),
+        ];
+        let retained = |keep_daily: u32, keep_weekly: u32, week_start: DayOfWeek| {
+            let config = BackupConfig {
+                directory: PathBuf::new(),
+                keep_daily,
+                keep_weekly,
+            };
+            retained_backups(&backups, &config, |date| date.date_naive(), week_start)
+        };
+
+        // the newest of the 16th and the 15th, which is also the newest of its week, and
+        // the newest of the week of the 2nd
+        assert_eq!(
+            retained(2, 3, DayOfWeek::Monday),
+            vec![true, false, true, true, true, false, true, false]
+        );
+        // with weeks starting on Sunday, the 15th shares a week with the 16th
+        assert_eq!(
+            retained(1, 3, DayOfWeek::Sunday),
+            vec![true, false, false, true, true, true, true, false]
+        );
+        // manual and pre-migration backups are kept whatever the limits
+        assert_eq!(
+            retained(1, 0, DayOfWeek::Monday),
+            vec![true, false, false, true, true, false, false, false]
+        );
+    }
+}
@@ -1,16 +1,31 @@
 // Habit Tracker Data Cleanup Binary
-// Cross-platform utility to remove application data using the dirs crate
-// Usage: cargo run --bin cleanup-data
+// Cross-platform utility to remove application data, after backing it up to an archive and
+// a copy of the config file
+// Usage: cargo run --bin cleanup-data -- [--dry-run] [--yes] [--no-backup]
+//        cargo run --bin cleanup-data -- --restore BACKUP [--yes]
 
+use chrono::Local;
+use habit_tracker_lib::{
+    app_config::config_file_path,
+    backup::read_backups,
+    export::{export_archive, import_archive, HabitTrackerArchive, ImportMode},
+    get_app_config, HabitTrackerService,
+};
+use std::error::Error;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-fn get_data_directory() -> Result<PathBuf, String> {
-    let mut path = dirs::data_dir().ok_or("Could not determine data directory")?;
-    path.push("HabitTracker");
-    Ok(path)
-}
+const USAGE: &str = "Usage:
+  cleanup-data [--dry-run] [--yes] [--no-backup]
+      delete the database, its backups and the config file, after writing a backup archive and
+      a copy of the config file next to the database
+      --dry-run     list what would be deleted, without deleting anything
+      --yes         do not ask for confirmation
+      --no-backup   do not back up first
+  cleanup-data --restore BACKUP [--yes]
+      replace the data of the database with a backup archive, and the config file with its
+      copy next to the archive if there is one";
 
 fn prompt_user(message: &str) -> bool {
     print!("{message}");
@@ -22,58 +37,284 @@ fn prompt_user(message: &str) -> bool {
     matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
-fn main() {
-    println!("🧹 Habit Tracker Data Cleanup");
-    println!("=============================");
-    println!();
+#[derive(Default)]
+struct Options<'a> {
+    dry_run: bool,
+    yes: bool,
+    no_backup: bool,
+    restore: Option<&'a str>,
+}
 
-    // Get the data directory path
-    let data_dir = match get_data_directory() {
-        Ok(path) => path,
-        Err(e) => {
-            eprintln!("Error: {e}");
-            std::process::exit(1);
+fn parse_options<'a>(mut args: &[&'a str]) -> Option<Options<'a>> {
+    let mut options = Options::default();
+    while let Some((arg, rest)) = args.split_first() {
+        args = rest;
+        match (*arg, args.split_first()) {
+            ("--dry-run", _) => options.dry_run = true,
+            ("--yes", _) => options.yes = true,
+            ("--no-backup", _) => options.no_backup = true,
+            ("--restore", Some((backup, rest))) => (options.restore, args) = (Some(*backup), rest),
+            _ => return None,
         }
+    }
+    // restoring neither deletes nor backs up anything
+    if options.restore.is_some() && (options.dry_run || options.no_backup) {
+        return None;
+    }
+    Some(options)
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let Some(options) = parse_options(&args) else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+
+    let result = match options.restore {
+        Some(backup) => restore(Path::new(backup), options.yes),
+        None => cleanup(&options),
     };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+// e.g. `12.3 KB`
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB"] {
+        if size < 1024.0 {
+            return if unit == "B" {
+                format!("{bytes} B")
+            } else {
+                format!("{size:.1} {unit}")
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} GB")
+}
 
-    println!("Data directory: {}", data_dir.display());
+fn cleanup(options: &Options) -> Result<(), Box<dyn Error>> {
+    println!("🧹 Habit Tracker Data Cleanup");
+    println!("=============================");
     println!();
 
-    // Check if directory exists
-    if !data_dir.exists() {
-        println!("Data directory doesn't exist - nothing to clean up!");
-        return;
+    let app_config = get_app_config()?;
+    let db_path = PathBuf::from(&app_config.db_path);
+    let config_path = config_file_path()?;
+    let files = data_files(&db_path, &config_path);
+    let backup_config = app_config.backups.clone();
+    let backups = read_backups(&db_path, &backup_config)?;
+
+    if files.is_empty() && backups.is_empty() {
+        println!("No habit tracker data found - nothing to clean up!");
+        return Ok(());
     }
 
-    // Show what will be removed
     println!("This will permanently remove:");
-    println!("• All habit tracking data");
-    println!("• Database files");
-    println!("• Configuration files");
-    println!("• Any cached data");
+    for (path, size) in &files {
+        println!("• {} ({})", path.display(), format_size(*size));
+    }
+    if !backups.is_empty() {
+        println!(
+            "• {} backups of the database in {} ({})",
+            backups.len(),
+            backup_config.directory.display(),
+            format_size(backups.iter().map(|backup| backup.size).sum())
+        );
+    }
     println!();
-    println!("⚠️  This action cannot be undone!");
+    let backup = (!options.no_backup && db_path.exists()).then(|| backup_path(&db_path));
+    let config_backup = backup
+        .as_ref()
+        .filter(|_| config_path.exists())
+        .map(|backup| backup.with_extension("toml"));
+    match &backup {
+        Some(backup) => println!("A backup will be written to {} first", backup.display()),
+        None => println!("⚠️  No backup will be written, this action cannot be undone!"),
+    }
+    if let Some(config_backup) = &config_backup {
+        println!(
+            "The config file will be copied to {}",
+            config_backup.display()
+        );
+    }
     println!();
 
-    // Confirm with user
-    if !prompt_user("Are you sure you want to delete all habit tracker data? (y/N): ") {
+    if options.dry_run {
+        println!("Dry run - nothing was deleted");
+        return Ok(());
+    }
+    if !options.yes
+        && !prompt_user("Are you sure you want to delete all habit tracker data? (y/N): ")
+    {
         println!("Cleanup cancelled");
-        return;
+        return Ok(());
+    }
+
+    if let Some(backup) = &backup {
+        // the service is dropped, closing the database, before it is deleted
+        let service = HabitTrackerService::build(app_config)?;
+        let archive = export_archive(&service)?;
+        fs::write(backup, serde_json::to_string_pretty(&archive)?)?;
+        println!(
+            "Backed up {} habits, {} entries and {} calendar events to {}",
+            archive.habits.len(),
+            archive.habit_entries.len(),
+            archive.calendar_events.len(),
+            backup.display()
+        );
+    }
+    if let Some(config_backup) = &config_backup {
+        fs::copy(&config_path, config_backup)?;
+        println!("Copied the config file to {}", config_backup.display());
     }
 
-    // Perform the cleanup
-    match fs::remove_dir_all(&data_dir) {
-        Ok(()) => {
-            println!("Successfully removed all habit tracker data");
-            println!("Removed: {}", data_dir.display());
+    // listed again, as opening and closing the database may add or remove its -wal and -shm
+    for (path, _) in data_files(&db_path, &config_path) {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            // removed meanwhile, e.g. -wal by SQLite closing the database
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to remove {}: {e}, you may need to remove it manually",
+                    path.display()
+                )
+                .into())
+            }
         }
-        Err(e) => {
-            eprintln!("Failed to remove data directory: {e}");
-            eprintln!("You may need to remove it manually: {}", data_dir.display());
-            std::process::exit(1);
+        println!("Removed: {}", path.display());
+    }
+    // only those of this database, others may share the directory
+    let backups = read_backups(&db_path, &backup_config)?;
+    for backup in &backups {
+        let path = backup_config.directory.join(&backup.name);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(format!(
+                    "Failed to remove {}: {e}, you may need to remove it manually",
+                    path.display()
+                )
+                .into())
+            }
         }
     }
+    if !backups.is_empty() {
+        println!(
+            "Removed {} backups from {}",
+            backups.len(),
+            backup_config.directory.display()
+        );
+    }
+    // fails, keeping it, when other files are left in it
+    let _ = fs::remove_dir(&backup_config.directory);
 
     println!();
     println!("Data cleanup complete!");
+    if let Some(backup) = &backup {
+        println!(
+            "Restore it with: cleanup-data --restore {}",
+            backup.display()
+        );
+    }
+    Ok(())
+}
+
+// The database, the files SQLite keeps uncommitted and not yet checkpointed changes in next to
+// it, and the config file, with their sizes. Only those that exist.
+fn data_files(db_path: &Path, config_path: &Path) -> Vec<(PathBuf, u64)> {
+    let mut files = ["", "-journal", "-wal", "-shm"]
+        .into_iter()
+        .map(|suffix| PathBuf::from(format!("{}{suffix}", db_path.display())))
+        .collect::<Vec<_>>();
+    files.push(config_path.to_path_buf());
+    files
+        .into_iter()
+        .filter_map(|path| {
+            fs::metadata(&path)
+                .ok()
+                .map(|metadata| (path, metadata.len()))
+        })
+        .collect()
+}
+
+// a timestamped archive next to the database, e.g. `habits-backup-20250601-203000.json`
+fn backup_path(db_path: &Path) -> PathBuf {
+    let stem = db_path
+        .file_stem()
+        .map_or("habits".into(), |stem| stem.to_string_lossy());
+    db_path.with_file_name(format!(
+        "{stem}-backup-{}.json",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+fn restore(backup: &Path, yes: bool) -> Result<(), Box<dyn Error>> {
+    let archive: HabitTrackerArchive = serde_json::from_str(&fs::read_to_string(backup)?)?;
+    // restored first, as it may name another database
+    let config_backup = backup.with_extension("toml");
+    if config_backup.exists() && !restore_config(&config_backup, yes)? {
+        println!("Restore cancelled");
+        return Ok(());
+    }
+    let app_config = get_app_config()?;
+    println!("Restoring {} into {}", backup.display(), app_config.db_path);
+    let mut service = HabitTrackerService::build(app_config)?;
+
+    let current = export_archive(&service)?;
+    let has_data = !current.habits.is_empty() || !current.habit_entries.is_empty();
+    if has_data
+        && !yes
+        && !prompt_user(&format!(
+            "This replaces the current {} habits and {} entries. Continue? (y/N): ",
+            current.habits.len(),
+            current.habit_entries.len()
+        ))
+    {
+        println!("Restore cancelled");
+        return Ok(());
+    }
+
+    let report = import_archive(&mut service, &archive, ImportMode::Replace)?;
+    println!(
+        "Restored {} habits, {} entries and {} calendar events",
+        report.habits.added, report.habit_entries.added, report.calendar_events.added
+    );
+    Ok(())
+}
+
+// false when cancelled
+fn restore_config(config_backup: &Path, yes: bool) -> Result<bool, Box<dyn Error>> {
+    let content = fs::read_to_string(config_backup)?;
+    let config_path = config_file_path()?;
+    match fs::read_to_string(&config_path) {
+        Ok(current) if current == content => return Ok(true),
+        Ok(_)
+            if !yes
+                && !prompt_user(&format!(
+                    "This replaces the config file {}. Continue? (y/N): ",
+                    config_path.display()
+                )) =>
+        {
+            return Ok(false)
+        }
+        _ => {}
+    }
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&config_path, content)?;
+    println!(
+        "Restored {} into {}",
+        config_backup.display(),
+        config_path.display()
+    );
+    Ok(true)
 }
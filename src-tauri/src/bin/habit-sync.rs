@@ -1,35 +1,95 @@
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-use std::{error::Error, sync::Mutex};
+// Habit Tracker Calendar Sync Binary
+// Syncs calendar events from the configured calendar source (Apple Calendar by default),
+// or imports them from iCalendar files
+// Usage: cargo run --bin habit-sync --features apple_calendar
+//        cargo run --bin habit-sync -- --ics FILE...
+//        cargo run --bin habit-sync -- --reminder-times
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-use habit_tracker_lib::{get_app_config, sync_apple_calendar_events_impl, HabitTrackerService};
+use habit_tracker_lib::{
+    api::CalendarSyncReport,
+    app_config::{config_file_path, read_config_file},
+    calendar_source::{build_calendar_source, CalendarSource, IcsCalendarSource},
+    get_app_config, AppConfig, HabitTrackerService,
+};
+use std::error::Error;
+use std::path::PathBuf;
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-fn main() -> Result<(), Box<dyn Error>> {
-    let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async_main())
-}
-
-#[cfg(not(all(target_os = "macos", feature = "apple_calendar")))]
 fn main() {
-    eprintln!("habit-sync binary requires 'apple_calendar' feature to be enabled");
-    std::process::exit(1);
-}
-
-#[cfg(feature = "apple_calendar")]
-async fn async_main() -> Result<(), Box<dyn Error>> {
-    let app_config = get_app_config()?;
-    let service = HabitTrackerService::build(app_config)?;
-
-    match sync_apple_calendar_events_impl(&Mutex::new(service)).await {
-        Ok(_) => {
-            println!("Calendar sync completed successfully");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args == ["--reminder-times"] {
+        if let Err(e) = print_reminder_times() {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let result = match args.split_first() {
+        None => sync_configured_source(),
+        Some((flag, paths)) if flag == "--ics" && !paths.is_empty() => import_ics(paths),
+        _ => {
+            eprintln!("Usage: habit-sync [--ics FILE... | --reminder-times]");
+            std::process::exit(2);
+        }
+    };
+    match result {
+        Ok(report) => {
+            for event in report.added.iter().chain(&report.updated) {
+                if let Some(error) = &event.recurrence_error {
+                    eprintln!(
+                        "Warning: \"{}\" can't be linked to habits, unsupported recurrence {}: {error}",
+                        event.name, event.recurrence
+                    );
+                }
+            }
+            for habit in &report.flagged_habits {
+                eprintln!(
+                    "Warning: \"{}\" is linked to an event removed from the calendar, relink it",
+                    habit.title
+                );
+            }
+            println!(
+                "Calendar sync completed successfully, {} added, {} updated, {} removed",
+                report.added.len(),
+                report.updated.len(),
+                report.removed.len()
+            );
         }
         Err(e) => {
             eprintln!("Sync failed: {e}");
             std::process::exit(1);
         }
     }
+}
 
+// For setup-background-services.sh: the sync time on the first line, then the notification
+// times separated by spaces
+fn print_reminder_times() -> Result<(), Box<dyn Error>> {
+    let config = read_config_file(&config_file_path()?)?;
+    println!("{}", config.reminders.sync_time);
+    println!("{}", config.reminders.notify_times.join(" "));
     Ok(())
 }
+
+fn sync_configured_source() -> Result<CalendarSyncReport, Box<dyn Error>> {
+    let app_config = get_app_config()?;
+    let source = build_calendar_source(&app_config.calendar_source, app_config.timezone);
+    sync(app_config, source.as_ref())
+}
+
+fn import_ics(paths: &[String]) -> Result<CalendarSyncReport, Box<dyn Error>> {
+    let app_config = get_app_config()?;
+    let source = IcsCalendarSource {
+        paths: paths.iter().map(PathBuf::from).collect(),
+        timezone: app_config.timezone,
+    };
+    sync(app_config, &source)
+}
+
+fn sync(
+    app_config: AppConfig,
+    source: &dyn CalendarSource,
+) -> Result<CalendarSyncReport, Box<dyn Error>> {
+    let events = source.events()?;
+    let mut service = HabitTrackerService::build(app_config)?;
+    Ok(service.sync_calendar_events(events)?)
+}
@@ -0,0 +1,564 @@
+// Habit Tracker Command-Line Client
+// Lists, creates and checks in habits from the terminal, on the database of the app
+// Usage: cargo run --bin habit -- status
+//        cargo run --bin habit -- done Run --date 2025-06-01
+// Every command accepts --json to print machine-readable output instead of text.
+
+use chrono::{Duration, Local, Months, NaiveDate};
+use habit_tracker_lib::{
+    api::{DayOfWeek, EventIds, HabitEntry, HabitKind, HabitType, Schedule, StatsGranularity},
+    backup::{create_backup, list_backups, restore_backup, BackupInfo, BackupReason},
+    doctor::DoctorIssue,
+    get_app_config, CreateHabitRequest, DateRange, FieldError, Habit, HabitTrackerError,
+    HabitTrackerService, InsertHabitEntriesRequest, InsertHabitEntryItem,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+const USAGE: &str = "Usage:
+  habit list [--all]                                 list habits, archived ones too with --all
+  habit add TITLE [--question TEXT] [--unit UNIT --target N]
+            [--weekdays mon,fri | --every N | --per-week N | --per-month N]
+                                                     create a daily habit
+  habit done HABIT [--date DATE] [--value N]         check in a habit, today by default,
+                                                     with the --value of measurable habits
+  habit undo HABIT [--date DATE]                     remove a check-in, today's by default
+  habit status [--date DATE]                         habits due today, and whether they are done
+  habit stats HABIT [--granularity week|month|year] [--from DATE --to DATE]
+                                                     completion rates, over the last year by default
+  habit backup list                                  list backups of the database, newest first
+  habit backup create                                back the database up now
+  habit backup restore NAME                          replace the database with a backup, after backing it up
+  habit doctor [--fix]                               check the database for issues, and fix them after
+                                                     backing it up with --fix. Fails when issues remain
+
+HABIT is a habit id or title. Add --json to any command for JSON output.
+The database is the app's, or the one at HABIT_TRACKER_DB_PATH.";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let json = args.iter().any(|arg| arg == "--json");
+    let args = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != "--json")
+        .collect::<Vec<_>>();
+    let Some((positional, options)) = parse_options(&args) else {
+        usage_error();
+    };
+    let result = match positional.as_slice() {
+        ["list"] => list(options.contains_key("--all"), json),
+        ["add", title] => add(title, &options, json),
+        ["done", habit] => done(habit, &options, json),
+        ["undo", habit] => undo(habit, &options, json),
+        ["status"] => status(&options, json),
+        ["stats", habit] => stats(habit, &options, json),
+        ["backup", "list"] => backup_list(json),
+        ["backup", "create"] => backup_create(json),
+        ["backup", "restore", name] => backup_restore(name, json),
+        ["doctor"] => doctor(options.contains_key("--fix"), json),
+        _ => usage_error(),
+    };
+    if let Err(e) = result {
+        // service errors keep their code, so scripts can branch on it
+        match e.downcast_ref::<HabitTrackerError>() {
+            Some(error) if json => eprintln!("{}", serde_json::json!(error)),
+            _ => eprintln!("Error: {e}"),
+        }
+        std::process::exit(1);
+    }
+}
+
+fn usage_error() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(2);
+}
+
+// options without a value
+const FLAGS: [&str; 2] = ["--all", "--fix"];
+
+// options taking a value
+const OPTIONS: [&str; 12] = [
+    "--question",
+    "--unit",
+    "--target",
+    "--weekdays",
+    "--every",
+    "--per-week",
+    "--per-month",
+    "--date",
+    "--value",
+    "--granularity",
+    "--from",
+    "--to",
+];
+
+/// Splits `args` into positional arguments and options. `None` when an option is unknown
+/// or misses its value.
+fn parse_options<'a>(mut args: &[&'a str]) -> Option<(Vec<&'a str>, HashMap<&'a str, &'a str>)> {
+    let mut positional = vec![];
+    let mut options = HashMap::new();
+    while let Some((arg, rest)) = args.split_first() {
+        args = rest;
+        if FLAGS.contains(arg) {
+            options.insert(*arg, "");
+        } else if OPTIONS.contains(arg) {
+            let (value, rest) = args.split_first()?;
+            options.insert(*arg, *value);
+            args = rest;
+        } else if arg.starts_with("--") {
+            return None;
+        } else {
+            positional.push(*arg);
+        }
+    }
+    Some((positional, options))
+}
+
+fn build_service() -> Result<HabitTrackerService, Box<dyn Error>> {
+    let app_config = get_app_config()?;
+    Ok(HabitTrackerService::build(app_config)?)
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, Box<dyn Error>> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {option}: {value}").into())
+}
+
+fn day_option(
+    service: &HabitTrackerService,
+    options: &HashMap<&str, &str>,
+    option: &str,
+) -> Result<NaiveDate, Box<dyn Error>> {
+    match options.get(option) {
+        Some(day) => parse_value(option, day),
+        None => Ok(service.today()),
+    }
+}
+
+/// The habit with id `query`, else the one titled `query`, ignoring case.
+fn find_habit(service: &HabitTrackerService, query: &str) -> Result<Habit, Box<dyn Error>> {
+    let mut habits = service.get_habits(true)?;
+    if let Ok(id) = query.parse::<i64>() {
+        if let Some(index) = habits.iter().position(|habit| habit.id == id) {
+            return Ok(habits.swap_remove(index));
+        }
+    }
+    habits.retain(|habit| habit.title.eq_ignore_ascii_case(query));
+    match habits.len() {
+        0 => Err(HabitTrackerError::NotFound {
+            message: format!("No habit with id or title {query}"),
+        }
+        .into()),
+        1 => Ok(habits.remove(0)),
+        _ => Err(HabitTrackerError::Conflict {
+            message: format!("Several habits are titled {query}, use an id"),
+        }
+        .into()),
+    }
+}
+
+const DAYS_OF_WEEK: [DayOfWeek; 7] = [
+    DayOfWeek::Monday,
+    DayOfWeek::Tuesday,
+    DayOfWeek::Wednesday,
+    DayOfWeek::Thursday,
+    DayOfWeek::Friday,
+    DayOfWeek::Saturday,
+    DayOfWeek::Sunday,
+];
+
+// e.g. `Mon`
+fn short_day_name(day: DayOfWeek) -> String {
+    format!("{day:?}")[..3].to_string()
+}
+
+fn describe_schedule(habit: &Habit) -> String {
+    if habit.habit_type == HabitType::AppleCalendar {
+        return "with calendar events".to_string();
+    }
+    match &habit.schedule {
+        Schedule::EveryDay => "every day".to_string(),
+        Schedule::Weekdays { days } => format!(
+            "on {}",
+            days.iter()
+                .map(|day| short_day_name(*day))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Schedule::EveryNDays { interval, .. } => format!("every {interval} days"),
+        Schedule::TimesPerWeek { count } => format!("{count} times a week"),
+        Schedule::TimesPerMonth { count } => format!("{count} times a month"),
+    }
+}
+
+fn describe_habit(habit: &Habit) -> String {
+    let target = match (habit.target, &habit.unit) {
+        (Some(target), Some(unit)) => format!(", {target} {unit}"),
+        (Some(target), None) => format!(", {target}"),
+        _ => String::new(),
+    };
+    let archived = if habit.archived_at.is_some() {
+        " (archived)"
+    } else {
+        ""
+    };
+    format!(
+        "{:>4}  {}{archived}  {}{target}",
+        habit.id,
+        habit.title,
+        describe_schedule(habit)
+    )
+}
+
+fn list(include_archived: bool, json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let habits = service.get_habits(include_archived)?;
+    if json {
+        return print_json(&habits);
+    }
+    for habit in &habits {
+        println!("{}", describe_habit(habit));
+    }
+    Ok(())
+}
+
+fn parse_schedule(
+    service: &HabitTrackerService,
+    options: &HashMap<&str, &str>,
+) -> Result<Schedule, Box<dyn Error>> {
+    let schedules = ["--weekdays", "--every", "--per-week", "--per-month"]
+        .into_iter()
+        .filter_map(|option| options.get(option).map(|value| (option, *value)))
+        .collect::<Vec<_>>();
+    let schedule = match schedules.as_slice() {
+        [] => Schedule::EveryDay,
+        [("--weekdays", days)] => Schedule::Weekdays {
+            days: days
+                .split(',')
+                .map(|day| {
+                    DAYS_OF_WEEK
+                        .into_iter()
+                        .find(|d| short_day_name(*d).eq_ignore_ascii_case(day.trim()))
+                        .ok_or_else(|| format!("Invalid --weekdays: {day}, expected e.g. mon,fri"))
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        [("--every", interval)] => Schedule::EveryNDays {
+            interval: parse_value("--every", interval)?,
+            start: service.today(),
+        },
+        [("--per-week", count)] => Schedule::TimesPerWeek {
+            count: parse_value("--per-week", count)?,
+        },
+        [("--per-month", count)] => Schedule::TimesPerMonth {
+            count: parse_value("--per-month", count)?,
+        },
+        _ => return Err("Only one of --weekdays, --every, --per-week and --per-month".into()),
+    };
+    Ok(schedule)
+}
+
+fn add(title: &str, options: &HashMap<&str, &str>, json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let target = options
+        .get("--target")
+        .map(|target| parse_value("--target", target))
+        .transpose()?;
+    let habit = service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds::default(),
+        title: title.to_string(),
+        question: options
+            .get("--question")
+            .map_or_else(|| format!("{title}?"), |question| question.to_string()),
+        kind: if target.is_some() {
+            HabitKind::Measurable
+        } else {
+            HabitKind::YesNo
+        },
+        unit: options.get("--unit").map(|unit| unit.to_string()),
+        target,
+        schedule: parse_schedule(&service, options)?,
+    })?;
+    if json {
+        return print_json(&habit);
+    }
+    println!("Added {}", describe_habit(&habit));
+    Ok(())
+}
+
+fn done(habit: &str, options: &HashMap<&str, &str>, json: bool) -> Result<(), Box<dyn Error>> {
+    let mut service = build_service()?;
+    let habit = find_habit(&service, habit)?;
+    let date = match options.get("--date") {
+        Some(day) => Some(service.date_on_day(parse_value("--date", day)?)),
+        None => None,
+    };
+    let value = options
+        .get("--value")
+        .map(|value| parse_value("--value", value))
+        .transpose()?;
+    // a check-in replaces the one of the same day, which must not lose its value
+    let message = match (habit.kind, value) {
+        (HabitKind::Measurable, None) => {
+            Some(format!("{} is measurable, give its --value", habit.title))
+        }
+        (HabitKind::YesNo, Some(_)) => Some(format!(
+            "{} is a yes/no habit, it has no --value",
+            habit.title
+        )),
+        _ => None,
+    };
+    if let Some(message) = message {
+        return Err(HabitTrackerError::validation(vec![FieldError {
+            field: "--value".to_string(),
+            message,
+        }])
+        .into());
+    }
+    let mut entries = service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: habit.id,
+            completed: true,
+            date,
+            value,
+        }],
+    })?;
+    let entry = entries.remove(0);
+    if json {
+        return print_json(&entry);
+    }
+    match (entry.completed, entry.value) {
+        (true, _) => println!("Done: {} on {}", habit.title, entry.day),
+        (false, Some(value)) => println!(
+            "Logged {value} for {} on {}, target not reached",
+            habit.title, entry.day
+        ),
+        (false, None) => println!("Logged {} on {}", habit.title, entry.day),
+    }
+    Ok(())
+}
+
+fn undo(habit: &str, options: &HashMap<&str, &str>, json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let habit = find_habit(&service, habit)?;
+    let day = day_option(&service, options, "--date")?;
+    let entry = service
+        .get_habit_entries()?
+        .into_iter()
+        .find(|entry| entry.habit_id == habit.id && entry.day == day)
+        .ok_or_else(|| HabitTrackerError::NotFound {
+            message: format!("{} has no check-in on {day}", habit.title),
+        })?;
+    service.delete_habit_entry(entry.id)?;
+    if json {
+        return print_json(&entry);
+    }
+    println!("Removed the check-in of {} on {day}", habit.title);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct HabitStatus {
+    habit: Habit,
+    entry: Option<HabitEntry>,
+    current_streak: u32,
+}
+
+fn status(options: &HashMap<&str, &str>, json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let day = day_option(&service, options, "--date")?;
+    let mut entries = service
+        .get_habit_entries()?
+        .into_iter()
+        .filter(|entry| entry.day == day)
+        .map(|entry| (entry.habit_id, entry))
+        .collect::<HashMap<_, _>>();
+    let streaks = service
+        .get_habit_streaks()?
+        .into_iter()
+        .map(|streak| (streak.habit_id, streak.current_streak))
+        .collect::<HashMap<_, _>>();
+    let statuses = service
+        .get_due_habits(day)?
+        .into_iter()
+        .map(|habit| HabitStatus {
+            entry: entries.remove(&habit.id),
+            current_streak: streaks.get(&habit.id).copied().unwrap_or_default(),
+            habit,
+        })
+        .collect::<Vec<_>>();
+    if json {
+        return print_json(&statuses);
+    }
+    if statuses.is_empty() {
+        println!("No habits due on {day}");
+    }
+    for status in &statuses {
+        let done = status.entry.as_ref().is_some_and(|entry| entry.completed);
+        println!(
+            "[{}] {:>4}  {}  streak {}",
+            if done { "x" } else { " " },
+            status.habit.id,
+            status.habit.title,
+            status.current_streak
+        );
+    }
+    Ok(())
+}
+
+fn stats(habit: &str, options: &HashMap<&str, &str>, json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let habit = find_habit(&service, habit)?;
+    let granularity = match options.get("--granularity").copied() {
+        None | Some("week") => StatsGranularity::Week,
+        Some("month") => StatsGranularity::Month,
+        Some("year") => StatsGranularity::Year,
+        Some(granularity) => return Err(format!("Invalid --granularity: {granularity}").into()),
+    };
+    let end = day_option(&service, options, "--to")?;
+    let start = match options.get("--from") {
+        Some(day) => parse_value("--from", day)?,
+        None => end - Months::new(12) + Duration::days(1),
+    };
+    let stats = service.get_habit_stats(habit.id, DateRange { start, end }, granularity)?;
+    if json {
+        return print_json(&stats);
+    }
+    println!("{} from {start} to {end}", habit.title);
+    for bucket in &stats.buckets {
+        println!(
+            "{:<10}  {:>3}/{:<3}  {:>3.0}%",
+            bucket.period,
+            bucket.completed_count,
+            bucket.due_count,
+            bucket.completion_rate * 100.0
+        );
+    }
+    Ok(())
+}
+
+fn describe_backup(backup: &BackupInfo) -> String {
+    format!(
+        "{}  {:?}, {}",
+        backup.name,
+        backup.reason,
+        backup
+            .created_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+    )
+}
+
+fn backup_list(json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let backups = list_backups(&service)?;
+    if json {
+        return print_json(&backups);
+    }
+    if backups.is_empty() {
+        println!("No backups yet");
+    }
+    for backup in &backups {
+        println!("{}", describe_backup(backup));
+    }
+    Ok(())
+}
+
+fn backup_create(json: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let backup = create_backup(&service, BackupReason::Manual)?;
+    if json {
+        return print_json(&backup);
+    }
+    println!("Backed up to {}", describe_backup(&backup));
+    Ok(())
+}
+
+fn backup_restore(name: &str, json: bool) -> Result<(), Box<dyn Error>> {
+    let mut service = build_service()?;
+    let previous = restore_backup(&mut service, name)?;
+    if json {
+        return print_json(&previous);
+    }
+    println!("Restored {name}");
+    println!("The replaced data was backed up to {}", previous.name);
+    Ok(())
+}
+
+fn describe_issue(issue: &DoctorIssue) -> String {
+    match issue {
+        DoctorIssue::Corruption { message } => format!("Database corruption: {message}"),
+        DoctorIssue::ForeignKeyViolation {
+            table,
+            row_id,
+            parent,
+        } => format!("{table} row {row_id} references a missing {parent}"),
+        DoctorIssue::OrphanEntry { entry_id, habit_id } => match habit_id {
+            Some(habit_id) => format!("Entry {entry_id} belongs to missing habit {habit_id}"),
+            None => format!("Entry {entry_id} belongs to no habit"),
+        },
+        DoctorIssue::DuplicateEntries {
+            habit_id,
+            day,
+            entry_ids,
+        } => format!(
+            "Habit {habit_id} has {} entries on {day}: {entry_ids:?}",
+            entry_ids.len()
+        ),
+        DoctorIssue::InvalidEventIds {
+            habit_id,
+            column,
+            value,
+        } => format!("Habit {habit_id} has invalid {column}: {value}"),
+        DoctorIssue::MissingEvent { habit_id, event_id } => {
+            format!("Habit {habit_id} is linked to missing calendar event {event_id}")
+        }
+        DoctorIssue::CalendarHabitWithoutEvents { habit_id, value } => format!(
+            "Calendar habit {habit_id} has no calendar events left: {value}, link it to other events or delete it"
+        ),
+    }
+}
+
+fn doctor(fix: bool, json: bool) -> Result<(), Box<dyn Error>> {
+    let mut service = build_service()?;
+    let report = service.doctor(fix)?;
+    let remaining = report
+        .issues
+        .iter()
+        .filter(|issue| !(report.fixed && issue.is_fixable()))
+        .count();
+    if json {
+        print_json(&report)?;
+    } else {
+        if report.issues.is_empty() {
+            println!("No issues found");
+        }
+        for issue in &report.issues {
+            let status = match (report.fixed, issue.is_fixable()) {
+                (true, true) => " (fixed)",
+                (false, true) => "",
+                (_, false) => " (can't be fixed, restore a backup)",
+            };
+            println!("{}{status}", describe_issue(issue));
+        }
+        if let Some(backup) = &report.backup {
+            println!("The database was backed up to {} first", backup.name);
+        }
+        if !report.fixed && report.issues.iter().any(DoctorIssue::is_fixable) {
+            println!("Run with --fix to fix them");
+        }
+    }
+    if remaining > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
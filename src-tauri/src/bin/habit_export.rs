@@ -0,0 +1,135 @@
+// Habit Tracker Export Binary
+// Exports the database to a JSON archive or CSV, or imports an archive into it
+// Usage: cargo run --bin habit-export -- export [FILE]
+//        cargo run --bin habit-export -- import FILE [--mode merge|replace]
+//        cargo run --bin habit-export -- csv [FILE] [--layout long|wide] [--from DATE --to DATE]
+
+use chrono::NaiveDate;
+use habit_tracker_lib::{
+    csv_export::{export_csv, CsvLayout},
+    export::{export_archive, import_archive, HabitTrackerArchive, ImportCounts, ImportMode},
+    get_app_config, DateRange, HabitTrackerService,
+};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+
+const USAGE: &str = "Usage:
+  habit-export export [FILE]                         write an archive to FILE, or stdout
+  habit-export import FILE [--mode merge|replace]    import an archive, merging by default
+  habit-export csv [FILE] [--layout long|wide] [--from DATE --to DATE]
+                                                     write entries as CSV to FILE, or stdout";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["export"] => export(None),
+        ["export", path] => export(Some(path)),
+        ["import", path] => import(path, ImportMode::Merge),
+        ["import", path, "--mode", mode] => match *mode {
+            "merge" => import(path, ImportMode::Merge),
+            "replace" => import(path, ImportMode::Replace),
+            _ => usage_error(),
+        },
+        ["csv", options @ ..] => match parse_csv_options(options) {
+            Some((path, layout, range)) => csv(path, layout, range),
+            None => usage_error(),
+        },
+        _ => usage_error(),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn usage_error() -> Result<(), Box<dyn Error>> {
+    eprintln!("{USAGE}");
+    std::process::exit(2);
+}
+
+fn build_service() -> Result<HabitTrackerService, Box<dyn Error>> {
+    let app_config = get_app_config()?;
+    Ok(HabitTrackerService::build(app_config)?)
+}
+
+fn export(path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    let archive = export_archive(&service)?;
+    let json = serde_json::to_string_pretty(&archive)?;
+    match path {
+        Some(path) => {
+            fs::write(path, json)?;
+            eprintln!(
+                "Exported {} habits, {} entries and {} calendar events to {path}",
+                archive.habits.len(),
+                archive.habit_entries.len(),
+                archive.calendar_events.len()
+            );
+        }
+        None => writeln!(io::stdout(), "{json}")?,
+    }
+    Ok(())
+}
+
+fn import(path: &str, mode: ImportMode) -> Result<(), Box<dyn Error>> {
+    let archive: HabitTrackerArchive = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut service = build_service()?;
+    let report = import_archive(&mut service, &archive, mode)?;
+    println!("Imported {path}");
+    print_counts("Habits", &report.habits);
+    print_counts("Entries", &report.habit_entries);
+    print_counts("Calendar events", &report.calendar_events);
+    Ok(())
+}
+
+fn parse_csv_options<'a>(
+    mut options: &[&'a str],
+) -> Option<(Option<&'a str>, CsvLayout, Option<DateRange>)> {
+    let mut path = None;
+    let mut layout = CsvLayout::Long;
+    let (mut from, mut to) = (None, None);
+    while let Some((option, rest)) = options.split_first() {
+        options = rest;
+        let date = |value: &str| value.parse::<NaiveDate>().ok();
+        match (*option, options.split_first()) {
+            ("--layout", Some((&"long", rest))) => (layout, options) = (CsvLayout::Long, rest),
+            ("--layout", Some((&"wide", rest))) => (layout, options) = (CsvLayout::Wide, rest),
+            ("--from", Some((value, rest))) => (from, options) = (Some(date(value)?), rest),
+            ("--to", Some((value, rest))) => (to, options) = (Some(date(value)?), rest),
+            (value, _) if path.is_none() && !value.starts_with("--") => path = Some(*option),
+            _ => return None,
+        }
+    }
+    let range = match (from, to) {
+        (Some(start), Some(end)) => Some(DateRange { start, end }),
+        (None, None) => None,
+        // wide exports have a row per day, so open ended ranges are not supported
+        _ => return None,
+    };
+    Some((path, layout, range))
+}
+
+fn csv(
+    path: Option<&str>,
+    layout: CsvLayout,
+    range: Option<DateRange>,
+) -> Result<(), Box<dyn Error>> {
+    let service = build_service()?;
+    match path {
+        Some(path) => {
+            export_csv(&service, fs::File::create(path)?, layout, range)?;
+            eprintln!("Exported entries to {path}");
+        }
+        None => export_csv(&service, io::stdout().lock(), layout, range)?,
+    }
+    Ok(())
+}
+
+fn print_counts(label: &str, counts: &ImportCounts) {
+    println!(
+        "{label}: {} added, {} skipped, {} conflicted",
+        counts.added, counts.skipped, counts.conflicted
+    );
+}
@@ -0,0 +1,140 @@
+use crate::{api::AppleCalendarEvent, error::HabitTrackerError, ics::read_ics_files};
+use chrono_tz::Tz;
+use std::{fs, path::PathBuf};
+
+/// Where calendar syncs read recurring events from.
+pub trait CalendarSource {
+    /// Every recurring event of the calendar, as `HabitTrackerService::sync_calendar_events`
+    /// expects them. Fails with `CalendarUnavailable` when the calendar can't be read.
+    fn events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError>;
+}
+
+/// The configured calendar source, see `build_calendar_source`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalendarSourceConfig {
+    /// Apple Calendar, through AppleScript. Only available on macOS with the
+    /// `apple_calendar` feature.
+    AppleScript { calendar_names: Vec<String> },
+    /// iCalendar files, read again on every sync.
+    Ics { paths: Vec<PathBuf> },
+    /// A directory of JSON files in the AppleScript output format, for development and tests.
+    Fixtures { directory: PathBuf },
+}
+
+impl CalendarSourceConfig {
+    pub fn is_available(&self) -> bool {
+        match self {
+            CalendarSourceConfig::AppleScript { .. } => {
+                cfg!(target_os = "macos") && cfg!(feature = "apple_calendar")
+            }
+            CalendarSourceConfig::Ics { .. } | CalendarSourceConfig::Fixtures { .. } => true,
+        }
+    }
+}
+
+/// `timezone` is the one floating iCalendar dates are read in.
+pub fn build_calendar_source(
+    config: &CalendarSourceConfig,
+    timezone: Tz,
+) -> Box<dyn CalendarSource + Send> {
+    match config {
+        CalendarSourceConfig::AppleScript { calendar_names } => {
+            Box::new(AppleScriptCalendarSource {
+                calendar_names: calendar_names.clone(),
+            })
+        }
+        CalendarSourceConfig::Ics { paths } => Box::new(IcsCalendarSource {
+            paths: paths.clone(),
+            timezone,
+        }),
+        CalendarSourceConfig::Fixtures { directory } => Box::new(FixtureCalendarSource {
+            directory: directory.clone(),
+        }),
+    }
+}
+
+/// Reads the calendars named `calendar_names`.
+pub struct AppleScriptCalendarSource {
+    pub calendar_names: Vec<String>,
+}
+
+impl CalendarSource for AppleScriptCalendarSource {
+    #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
+    fn events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+        // Embed the AppleScript content in the binary
+        let applescript_content =
+            include_str!("../../scripts/get_recurring_calendar_events.applescript");
+
+        let output_utf8 = std::process::Command::new("osascript")
+            .args(["-e", applescript_content])
+            .args(&self.calendar_names)
+            .output()
+            .map_err(|e| HabitTrackerError::CalendarUnavailable {
+                message: e.to_string(),
+            })?;
+        if !output_utf8.status.success() {
+            return Err(HabitTrackerError::CalendarUnavailable {
+                message: String::from_utf8_lossy(&output_utf8.stderr).into_owned(),
+            });
+        }
+        parse_events_json(&output_utf8.stdout).map_err(|e| HabitTrackerError::CalendarUnavailable {
+            message: e.to_string(),
+        })
+    }
+
+    #[cfg(not(all(target_os = "macos", feature = "apple_calendar")))]
+    fn events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+        Err(HabitTrackerError::CalendarUnavailable {
+            message: "Syncing from Apple Calendar requires macOS and the 'apple_calendar' feature"
+                .to_string(),
+        })
+    }
+}
+
+pub struct IcsCalendarSource {
+    pub paths: Vec<PathBuf>,
+    pub timezone: Tz,
+}
+
+impl CalendarSource for IcsCalendarSource {
+    fn events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+        read_ics_files(&self.paths, self.timezone)
+    }
+}
+
+/// Reads the `.json` files of a directory in name order, each an array of events.
+pub struct FixtureCalendarSource {
+    pub directory: PathBuf,
+}
+
+impl CalendarSource for FixtureCalendarSource {
+    fn events(&self) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+        let unavailable =
+            |path: &PathBuf, message: String| HabitTrackerError::CalendarUnavailable {
+                message: format!("{}: {message}", path.display()),
+            };
+        let mut paths = fs::read_dir(&self.directory)
+            .map_err(|e| unavailable(&self.directory, e.to_string()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| unavailable(&self.directory, e.to_string()))?;
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        });
+        paths.sort();
+        let mut events = vec![];
+        for path in paths {
+            let content = fs::read(&path).map_err(|e| unavailable(&path, e.to_string()))?;
+            events.extend(
+                parse_events_json(&content).map_err(|e| unavailable(&path, e.to_string()))?,
+            );
+        }
+        Ok(events)
+    }
+}
+
+// Start dates are unix timestamps, as the AppleScript writes them.
+fn parse_events_json(content: &[u8]) -> serde_json::Result<Vec<AppleCalendarEvent>> {
+    serde_json::from_slice(content)
+}
@@ -0,0 +1,155 @@
+use crate::{
+    api::HabitKind, error::HabitTrackerError, validation::validate_date_range, DateRange,
+    HabitTrackerService,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum CsvLayout {
+    /// One row per habit entry.
+    Long,
+    /// One row per day, one column per habit.
+    Wide,
+}
+
+const LONG_HEADER: [&str; 7] = [
+    "habit_id",
+    "habit_title",
+    "day",
+    "completed",
+    "value",
+    "unit",
+    "recorded_at",
+];
+
+/// Writes the entries within `range`, or all of them, as CSV. Rows are written as they are
+/// read from the database so exports of long histories don't hold every entry in memory.
+/// Completion is written as 1 or 0 so it can be summed.
+pub fn export_csv<W: Write>(
+    service: &HabitTrackerService,
+    writer: W,
+    layout: CsvLayout,
+    range: Option<DateRange>,
+) -> Result<(), HabitTrackerError> {
+    if let Some(range) = &range {
+        validate_date_range(range)?;
+    }
+    let mut writer = csv::Writer::from_writer(writer);
+    match layout {
+        CsvLayout::Long => write_long(service, &mut writer, range)?,
+        CsvLayout::Wide => write_wide(service, &mut writer, range)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_long<W: Write>(
+    service: &HabitTrackerService,
+    writer: &mut csv::Writer<W>,
+    range: Option<DateRange>,
+) -> Result<(), HabitTrackerError> {
+    writer.write_record(LONG_HEADER)?;
+    let mut statement = service.conn.prepare(
+        "SELECT habit.id, habit.title, habitEntry.day, habitEntry.completed, habitEntry.value, habit.unit, habitEntry.date
+        FROM habitEntry JOIN habit ON habit.id = habitEntry.habitId
+        WHERE (?1 IS NULL OR habitEntry.day >= ?1) AND (?2 IS NULL OR habitEntry.day <= ?2)
+        ORDER BY habitEntry.day, habit.id",
+    )?;
+    let mut rows = statement.query(params![
+        range.map(|range| range.start),
+        range.map(|range| range.end)
+    ])?;
+    while let Some(row) = rows.next()? {
+        let habit_id: i64 = row.get(0)?;
+        let title: String = row.get(1)?;
+        let day: NaiveDate = row.get(2)?;
+        let completed: bool = row.get(3)?;
+        let value: Option<f64> = row.get(4)?;
+        let unit: Option<String> = row.get(5)?;
+        let date: DateTime<Utc> = row.get(6)?;
+        writer.write_record([
+            habit_id.to_string(),
+            title,
+            day.to_string(),
+            u8::from(completed).to_string(),
+            value.map(|value| value.to_string()).unwrap_or_default(),
+            unit.unwrap_or_default(),
+            date.to_rfc3339(),
+        ])?;
+    }
+    Ok(())
+}
+
+// Every day of the range gets a row, days without entries are left blank. Measurable habits
+// show their value, yes/no habits their completion.
+fn write_wide<W: Write>(
+    service: &HabitTrackerService,
+    writer: &mut csv::Writer<W>,
+    range: Option<DateRange>,
+) -> Result<(), HabitTrackerError> {
+    let start = range.map(|range| range.start);
+    let end = range.map(|range| range.end);
+    let mut statement = service.conn.prepare(
+        "SELECT habit.id, habit.title, habit.kind, MIN(habitEntry.day), MAX(habitEntry.day)
+        FROM habit JOIN habitEntry ON habitEntry.habitId = habit.id
+        WHERE (?1 IS NULL OR habitEntry.day >= ?1) AND (?2 IS NULL OR habitEntry.day <= ?2)
+        GROUP BY habit.id
+        ORDER BY habit.id",
+    )?;
+    let mut habits = vec![];
+    // without a range, from the first to the last day with an entry
+    let mut days = range.map(|range| (range.start, range.end));
+    let mut rows = statement.query(params![start, end])?;
+    while let Some(row) = rows.next()? {
+        let (id, title, kind): (i64, String, HabitKind) = (row.get(0)?, row.get(1)?, row.get(2)?);
+        habits.push((id, title, kind));
+        let (min_day, max_day): (NaiveDate, NaiveDate) = (row.get(3)?, row.get(4)?);
+        if range.is_none() {
+            days = Some(match days {
+                Some((first_day, last_day)) => (first_day.min(min_day), last_day.max(max_day)),
+                None => (min_day, max_day),
+            });
+        }
+    }
+
+    writer.write_record(
+        std::iter::once("day").chain(habits.iter().map(|(_, title, _)| title.as_str())),
+    )?;
+    let Some((first_day, last_day)) = days else {
+        return Ok(());
+    };
+    let mut statement = service.conn.prepare(
+        "SELECT habitId, day, completed, value FROM habitEntry
+        WHERE day >= ?1 AND day <= ?2
+        ORDER BY day",
+    )?;
+    let mut rows = statement.query(params![first_day, last_day])?;
+    let mut next_entry = rows.next()?;
+    for day in first_day.iter_days().take_while(|day| *day <= last_day) {
+        let mut cells = vec![String::new(); habits.len()];
+        while let Some(row) = next_entry {
+            let entry_day: NaiveDate = row.get(1)?;
+            if entry_day != day {
+                break;
+            }
+            let habit_id: i64 = row.get(0)?;
+            if let Some(column) = habits.iter().position(|(id, _, _)| *id == habit_id) {
+                let completed: bool = row.get(2)?;
+                let value: Option<f64> = row.get(3)?;
+                cells[column] = match habits[column].2 {
+                    HabitKind::Measurable => {
+                        value.map(|value| value.to_string()).unwrap_or_default()
+                    }
+                    HabitKind::YesNo => u8::from(completed).to_string(),
+                };
+            }
+            next_entry = rows.next()?;
+        }
+        writer.write_record(std::iter::once(day.to_string()).chain(cells))?;
+    }
+    Ok(())
+}
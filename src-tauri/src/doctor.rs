@@ -0,0 +1,289 @@
+use crate::{
+    api::{EventIds, HabitTrackerService},
+    backup::{create_backup, BackupInfo, BackupReason},
+    error::HabitTrackerError,
+};
+use chrono::NaiveDate;
+use rusqlite::{params, types::Value, Connection};
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+pub enum DoctorIssue {
+    /// Reported by `PRAGMA integrity_check`. Can't be fixed, restore a backup instead.
+    Corruption { message: String },
+    /// Reported by `PRAGMA foreign_key_check`, for other rows than entries. Can't be fixed.
+    ForeignKeyViolation {
+        table: String,
+        row_id: i64,
+        parent: String,
+    },
+    /// An entry of a missing habit, or of none. Fixed by deleting the entry.
+    OrphanEntry {
+        entry_id: i64,
+        habit_id: Option<i64>,
+    },
+    /// Several entries of a habit on the same day. Fixed by keeping the latest one.
+    DuplicateEntries {
+        habit_id: i64,
+        day: NaiveDate,
+        entry_ids: Vec<i64>,
+    },
+    /// `eventIds` or `removedEventIds` that isn't a JSON array of strings, which keeps
+    /// habits from being read at all. Fixed by clearing the column.
+    InvalidEventIds {
+        habit_id: i64,
+        column: String,
+        value: String,
+    },
+    /// An event linked to a habit that isn't in the database, not even as a tombstone.
+    /// Fixed by unlinking it.
+    MissingEvent { habit_id: i64, event_id: String },
+    /// A calendar habit whose `eventIds` can't be read or only link missing events. Can't be
+    /// fixed, as calendar habits need events: link it to other events, or delete it.
+    CalendarHabitWithoutEvents { habit_id: i64, value: String },
+}
+
+impl DoctorIssue {
+    pub fn is_fixable(&self) -> bool {
+        !matches!(
+            self,
+            DoctorIssue::Corruption { .. }
+                | DoctorIssue::ForeignKeyViolation { .. }
+                | DoctorIssue::CalendarHabitWithoutEvents { .. }
+        )
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Type)]
+pub struct DoctorReport {
+    pub issues: Vec<DoctorIssue>,
+    /// Whether the fixable issues were fixed.
+    pub fixed: bool,
+    /// Taken before fixing, `None` for in-memory databases or when nothing was fixed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<BackupInfo>,
+}
+
+fn check_integrity(conn: &Connection) -> rusqlite::Result<Vec<DoctorIssue>> {
+    let mut statement = conn.prepare("PRAGMA integrity_check")?;
+    let messages = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(messages
+        .into_iter()
+        .filter(|message| message != "ok")
+        .map(|message| DoctorIssue::Corruption { message })
+        .collect())
+}
+
+fn check_foreign_keys(conn: &Connection) -> rusqlite::Result<Vec<DoctorIssue>> {
+    let mut statement = conn.prepare("PRAGMA foreign_key_check")?;
+    let violations = statement
+        .query_map([], |row| {
+            Ok(DoctorIssue::ForeignKeyViolation {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                parent: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    // those of entries are the orphan entries
+    Ok(violations
+        .into_iter()
+        .filter(|issue| {
+            !matches!(issue, DoctorIssue::ForeignKeyViolation { table, .. } if table == "habitEntry")
+        })
+        .collect())
+}
+
+fn check_orphan_entries(conn: &Connection) -> rusqlite::Result<Vec<DoctorIssue>> {
+    let mut statement = conn.prepare(
+        "SELECT id, habitId FROM habitEntry WHERE habitId IS NULL OR habitId NOT IN (SELECT id FROM habit) ORDER BY id",
+    )?;
+    let issues = statement
+        .query_map([], |row| {
+            Ok(DoctorIssue::OrphanEntry {
+                entry_id: row.get(0)?,
+                habit_id: row.get(1)?,
+            })
+        })?
+        .collect();
+    issues
+}
+
+// the unique index on habit and day prevents them, unless it went missing
+fn check_duplicate_entries(conn: &Connection) -> rusqlite::Result<Vec<DoctorIssue>> {
+    let mut statement = conn.prepare(
+        "SELECT habitId, day, id FROM habitEntry WHERE (habitId, day) IN (SELECT habitId, day FROM habitEntry WHERE habitId IS NOT NULL GROUP BY habitId, day HAVING COUNT(*) > 1) ORDER BY habitId, day, id",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut issues = vec![];
+    while let Some(row) = rows.next()? {
+        let (row_habit_id, row_day, entry_id): (i64, NaiveDate, i64) =
+            (row.get(0)?, row.get(1)?, row.get(2)?);
+        match issues.last_mut() {
+            Some(DoctorIssue::DuplicateEntries {
+                habit_id,
+                day,
+                entry_ids,
+            }) if *habit_id == row_habit_id && *day == row_day => entry_ids.push(entry_id),
+            _ => issues.push(DoctorIssue::DuplicateEntries {
+                habit_id: row_habit_id,
+                day: row_day,
+                entry_ids: vec![entry_id],
+            }),
+        }
+    }
+    Ok(issues)
+}
+
+fn raw_value(value: Value) -> String {
+    match value {
+        Value::Text(text) => text,
+        value => format!("{value:?}"),
+    }
+}
+
+// read as raw values, as `EventIds` fails on anything but a JSON array of strings
+fn parse_event_ids(value: Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Null => Ok(vec![]),
+        Value::Text(text) => serde_json::from_str(&text).map_err(|_| text),
+        value => Err(raw_value(value)),
+    }
+}
+
+fn check_event_ids(conn: &Connection) -> rusqlite::Result<Vec<DoctorIssue>> {
+    let event_ids = conn
+        .prepare("SELECT id FROM appleCalendarEvent")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    let mut statement = conn.prepare(
+        "SELECT id, habitType = 'appleCalendar', eventIds, removedEventIds FROM habit ORDER BY id",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut issues = vec![];
+    while let Some(row) = rows.next()? {
+        let habit_id: i64 = row.get(0)?;
+        let is_calendar_habit: bool = row.get(1)?;
+        let value: Value = row.get(2)?;
+        match parse_event_ids(value.clone()) {
+            // unlinking would leave the habit without events, which it can't be saved with
+            Ok(values)
+                if is_calendar_habit
+                    && values.iter().all(|event_id| !event_ids.contains(event_id)) =>
+            {
+                issues.push(DoctorIssue::CalendarHabitWithoutEvents {
+                    habit_id,
+                    value: raw_value(value),
+                });
+            }
+            Err(value) if is_calendar_habit => {
+                issues.push(DoctorIssue::CalendarHabitWithoutEvents { habit_id, value });
+            }
+            Ok(values) => issues.extend(
+                values
+                    .into_iter()
+                    .filter(|event_id| !event_ids.contains(event_id))
+                    .map(|event_id| DoctorIssue::MissingEvent { habit_id, event_id }),
+            ),
+            Err(value) => issues.push(DoctorIssue::InvalidEventIds {
+                habit_id,
+                column: "eventIds".to_string(),
+                value,
+            }),
+        }
+        // removed events keep their row, so only linked ones are checked for missing events
+        if let Err(value) = parse_event_ids(row.get(3)?) {
+            issues.push(DoctorIssue::InvalidEventIds {
+                habit_id,
+                column: "removedEventIds".to_string(),
+                value,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+impl HabitTrackerService {
+    /// Checks the database for corruption and for data the app can't read or shouldn't have
+    /// written. With `fix`, repairs what it can in a transaction, after backing the database up.
+    pub fn doctor(&mut self, fix: bool) -> Result<DoctorReport, HabitTrackerError> {
+        let mut issues = check_integrity(&self.conn)?;
+        issues.extend(check_foreign_keys(&self.conn)?);
+        issues.extend(check_orphan_entries(&self.conn)?);
+        issues.extend(check_duplicate_entries(&self.conn)?);
+        issues.extend(check_event_ids(&self.conn)?);
+        if !fix || !issues.iter().any(DoctorIssue::is_fixable) {
+            return Ok(DoctorReport {
+                issues,
+                ..Default::default()
+            });
+        }
+
+        let backup = match self.db_path {
+            Some(_) => Some(create_backup(self, BackupReason::PreRepair)?),
+            None => None,
+        };
+        let transaction = self.conn.transaction()?;
+        for issue in &issues {
+            match issue {
+                DoctorIssue::Corruption { .. }
+                | DoctorIssue::ForeignKeyViolation { .. }
+                | DoctorIssue::CalendarHabitWithoutEvents { .. } => {}
+                DoctorIssue::OrphanEntry { entry_id, .. } => {
+                    transaction
+                        .execute("DELETE FROM habitEntry WHERE id = ?1", params![entry_id])?;
+                }
+                DoctorIssue::DuplicateEntries { entry_ids, .. } => {
+                    // the latest one, as when entries became unique per day
+                    let older_entry_ids =
+                        entry_ids.split_last().map_or(&[][..], |(_, older)| older);
+                    for entry_id in older_entry_ids {
+                        transaction
+                            .execute("DELETE FROM habitEntry WHERE id = ?1", params![entry_id])?;
+                    }
+                }
+                DoctorIssue::InvalidEventIds {
+                    habit_id, column, ..
+                } => {
+                    transaction.execute(
+                        &format!("UPDATE habit SET {column} = NULL WHERE id = ?1"),
+                        params![habit_id],
+                    )?;
+                }
+                DoctorIssue::MissingEvent { habit_id, event_id } => {
+                    let event_ids: EventIds = transaction.query_row(
+                        "SELECT eventIds FROM habit WHERE id = ?1",
+                        params![habit_id],
+                        |row| row.get(0),
+                    )?;
+                    let event_ids = EventIds {
+                        values: event_ids
+                            .values
+                            .into_iter()
+                            .filter(|id| id != event_id)
+                            .collect(),
+                    };
+                    transaction.execute(
+                        "UPDATE habit SET eventIds = ?1 WHERE id = ?2",
+                        params![event_ids, habit_id],
+                    )?;
+                }
+            }
+        }
+        // see check_duplicate_entries
+        transaction.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS habitEntry_habitId_day ON habitEntry(habitId, day)",
+            [],
+        )?;
+        transaction.commit()?;
+        Ok(DoctorReport {
+            issues,
+            fixed: true,
+            backup,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use crate::migrations::MigrationError;
+use rusqlite::ErrorCode;
+use serde::Serialize;
+use specta::Type;
+use std::{error::Error, fmt};
+
+// Serialized as `{ code, message, ... }` so the frontend can branch on `code`,
+// which must stay stable across releases.
+#[derive(Debug, PartialEq, Serialize, Type)]
+#[serde(tag = "code")]
+pub enum HabitTrackerError {
+    NotFound {
+        message: String,
+    },
+    Validation {
+        message: String,
+        fields: Vec<FieldError>,
+    },
+    Conflict {
+        message: String,
+    },
+    Storage {
+        message: String,
+    },
+    CalendarUnavailable {
+        message: String,
+    },
+}
+
+/// A rejected request field, named as in the request, e.g. `title` or `data[1].habit_id`.
+#[derive(Debug, PartialEq, Serialize, Type)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl HabitTrackerError {
+    pub fn validation(fields: Vec<FieldError>) -> Self {
+        let message = fields
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HabitTrackerError::Validation { message, fields }
+    }
+}
+
+impl fmt::Display for HabitTrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HabitTrackerError::NotFound { message }
+            | HabitTrackerError::Validation { message, .. }
+            | HabitTrackerError::Conflict { message }
+            | HabitTrackerError::Storage { message }
+            | HabitTrackerError::CalendarUnavailable { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for HabitTrackerError {}
+
+impl From<rusqlite::Error> for HabitTrackerError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => HabitTrackerError::NotFound {
+                message: e.to_string(),
+            },
+            rusqlite::Error::SqliteFailure(failure, _)
+                if failure.code == ErrorCode::ConstraintViolation =>
+            {
+                HabitTrackerError::Conflict {
+                    message: e.to_string(),
+                }
+            }
+            _ => HabitTrackerError::Storage {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<csv::Error> for HabitTrackerError {
+    fn from(e: csv::Error) -> Self {
+        HabitTrackerError::Storage {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<std::io::Error> for HabitTrackerError {
+    fn from(e: std::io::Error) -> Self {
+        HabitTrackerError::Storage {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<r2d2::Error> for HabitTrackerError {
+    fn from(e: r2d2::Error) -> Self {
+        HabitTrackerError::Storage {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<MigrationError> for HabitTrackerError {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::Sqlite(e) => e.into(),
+            MigrationError::UnsupportedVersion { .. } => HabitTrackerError::Storage {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use crate::error::{FieldError, HabitTrackerError};
+    use rusqlite::{params, Connection, Result};
+
+    #[test]
+    fn test_habit_tracker_error_from_rusqlite() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("CREATE TABLE habit (id INTEGER PRIMARY KEY)")?;
+        conn.execute("INSERT INTO habit (id) VALUES (?1)", params![1])?;
+
+        let not_found = conn
+            .query_row("SELECT id FROM habit WHERE id = ?1", params![2], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap_err();
+        assert!(matches!(
+            HabitTrackerError::from(not_found),
+            HabitTrackerError::NotFound { .. }
+        ));
+        let conflict = conn
+            .execute("INSERT INTO habit (id) VALUES (?1)", params![1])
+            .unwrap_err();
+        assert!(matches!(
+            HabitTrackerError::from(conflict),
+            HabitTrackerError::Conflict { .. }
+        ));
+        let storage = conn.execute("SELECT * FROM missing", []).unwrap_err();
+        assert!(matches!(
+            HabitTrackerError::from(storage),
+            HabitTrackerError::Storage { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_habit_tracker_error_serialization() {
+        assert_eq!(
+            serde_json::to_value(HabitTrackerError::NotFound {
+                message: "habit 1 not found".into()
+            })
+            .unwrap(),
+            serde_json::json!({ "code": "NotFound", "message": "habit 1 not found" })
+        );
+        assert_eq!(
+            serde_json::to_value(HabitTrackerError::validation(vec![FieldError {
+                field: "title".into(),
+                message: "Too short".into(),
+            }]))
+            .unwrap(),
+            serde_json::json!({
+                "code": "Validation",
+                "message": "title: Too short",
+                "fields": [{ "field": "title", "message": "Too short" }],
+            })
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use crate::{
+    api::{AppleCalendarEvent, ExcludedDates, Habit, HabitEntry},
+    error::{FieldError, HabitTrackerError},
+    HabitTrackerService,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{HashMap, HashSet};
+
+// Bump when the archive layout changes. Archives from newer versions are refused on import,
+// older ones are read with defaults for what they lack. Version 2 added the excluded dates,
+// parent and removal time of calendar events.
+pub const EXPORT_FORMAT_VERSION: u32 = 2;
+
+/// Portable copy of the whole database. Ids are only meaningful within the archive,
+/// they are remapped on import.
+#[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
+pub struct HabitTrackerArchive {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub habits: Vec<Habit>,
+    pub habit_entries: Vec<HabitEntry>,
+    pub calendar_events: Vec<ArchivedCalendarEvent>,
+}
+
+// `AppleCalendarEvent` reads its start date as a unix timestamp from the AppleScript output,
+// archived events keep it as RFC 3339 like every other date in the archive.
+#[derive(Debug, PartialEq, Deserialize, Serialize, Type)]
+pub struct ArchivedCalendarEvent {
+    pub id: String,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub recurrence: String,
+    #[serde(default)]
+    pub excluded_dates: ExcludedDates,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Set on events removed from the calendar, which habits may still link to.
+    #[serde(default)]
+    pub removed_at: Option<DateTime<Utc>>,
+}
+
+impl From<AppleCalendarEvent> for ArchivedCalendarEvent {
+    fn from(event: AppleCalendarEvent) -> Self {
+        ArchivedCalendarEvent {
+            id: event.id,
+            name: event.name,
+            start_date: event.start_date,
+            recurrence: event.recurrence,
+            excluded_dates: event.excluded_dates,
+            parent_id: event.parent_id,
+            removed_at: event.removed_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum ImportMode {
+    /// Delete everything, then import the archive.
+    Replace,
+    /// Keep existing data and add what is missing from it.
+    Merge,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Type)]
+pub struct ImportCounts {
+    pub added: u32,
+    pub skipped: u32,
+    pub conflicted: u32,
+}
+
+/// In merge mode, records already present unchanged are skipped, records present with
+/// different contents are conflicted and keep their current contents.
+#[derive(Debug, Default, PartialEq, Serialize, Type)]
+pub struct ImportReport {
+    pub habits: ImportCounts,
+    pub habit_entries: ImportCounts,
+    pub calendar_events: ImportCounts,
+}
+
+pub fn export_archive(
+    service: &HabitTrackerService,
+) -> Result<HabitTrackerArchive, HabitTrackerError> {
+    Ok(HabitTrackerArchive {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        habits: service.get_habits(true)?,
+        habit_entries: service.get_habit_entries()?,
+        // tombstones too, as habits keep linking to removed events
+        calendar_events: service
+            .conn
+            .prepare("SELECT * FROM appleCalendarEvent")?
+            .query_map([], AppleCalendarEvent::from_row)?
+            .map(|event| event.map(ArchivedCalendarEvent::from))
+            .collect::<rusqlite::Result<_>>()?,
+    })
+}
+
+pub fn import_archive(
+    service: &mut HabitTrackerService,
+    archive: &HabitTrackerArchive,
+    mode: ImportMode,
+) -> Result<ImportReport, HabitTrackerError> {
+    validate_archive(archive)?;
+    let mut report = ImportReport::default();
+    let transaction = service.conn.transaction()?;
+    if mode == ImportMode::Replace {
+        transaction.execute_batch(
+            "DELETE FROM habitEntry; DELETE FROM habit; DELETE FROM appleCalendarEvent;",
+        )?;
+    }
+    for event in &archive.calendar_events {
+        import_calendar_event(&transaction, event, &mut report.calendar_events)?;
+    }
+    // archive habit id -> habit id in this database
+    let mut habit_ids = HashMap::with_capacity(archive.habits.len());
+    for habit in &archive.habits {
+        let id = import_habit(&transaction, habit, mode, &mut report.habits)?;
+        habit_ids.insert(habit.id, id);
+    }
+    for entry in &archive.habit_entries {
+        import_habit_entry(
+            &transaction,
+            entry,
+            habit_ids[&entry.habit_id],
+            &mut report.habit_entries,
+        )?;
+    }
+    transaction.commit()?;
+    Ok(report)
+}
+
+fn validate_archive(archive: &HabitTrackerArchive) -> Result<(), HabitTrackerError> {
+    let mut errors = vec![];
+    if archive.format_version > EXPORT_FORMAT_VERSION {
+        errors.push(FieldError {
+            field: "format_version".into(),
+            message: format!(
+                "Archive format version {} is newer than the supported version {EXPORT_FORMAT_VERSION}",
+                archive.format_version
+            ),
+        });
+    }
+    let habit_ids = archive
+        .habits
+        .iter()
+        .map(|habit| habit.id)
+        .collect::<HashSet<_>>();
+    for (index, entry) in archive.habit_entries.iter().enumerate() {
+        if !habit_ids.contains(&entry.habit_id) {
+            errors.push(FieldError {
+                field: format!("habit_entries[{index}].habit_id"),
+                message: format!("Unknown habit {}", entry.habit_id),
+            });
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(HabitTrackerError::validation(errors))
+    }
+}
+
+fn import_calendar_event(
+    transaction: &Transaction,
+    event: &ArchivedCalendarEvent,
+    counts: &mut ImportCounts,
+) -> Result<(), HabitTrackerError> {
+    let existing = transaction
+        .query_row(
+            "SELECT * FROM appleCalendarEvent WHERE id = ?1",
+            params![event.id],
+            AppleCalendarEvent::from_row,
+        )
+        .optional()?
+        .map(ArchivedCalendarEvent::from);
+    match existing {
+        Some(existing) if existing == *event => counts.skipped += 1,
+        // tombstones are replaced by the archived event
+        Some(existing) if existing.removed_at.is_none() => counts.conflicted += 1,
+        _ => {
+            AppleCalendarEvent {
+                id: event.id.clone(),
+                name: event.name.clone(),
+                start_date: event.start_date,
+                recurrence: event.recurrence.clone(),
+                excluded_dates: event.excluded_dates.clone(),
+                parent_id: event.parent_id.clone(),
+                recurrence_rule: None,
+                recurrence_error: None,
+                removed_at: None,
+            }
+            .upsert(transaction)?;
+            transaction.execute(
+                "UPDATE appleCalendarEvent SET removedAt = ?1 WHERE id = ?2",
+                params![event.removed_at, event.id],
+            )?;
+            counts.added += 1;
+        }
+    }
+    Ok(())
+}
+
+// Habits have no stable identifier across databases, when merging a habit of the same
+// type and title is considered the same habit.
+fn import_habit(
+    transaction: &Transaction,
+    habit: &Habit,
+    mode: ImportMode,
+    counts: &mut ImportCounts,
+) -> Result<i64, HabitTrackerError> {
+    let existing = match mode {
+        ImportMode::Replace => None,
+        ImportMode::Merge => transaction
+            .query_row(
+                "SELECT * FROM habit WHERE habitType = ?1 AND title = ?2",
+                params![habit.habit_type, habit.title],
+                Habit::from_row,
+            )
+            .optional()?,
+    };
+    match existing {
+        Some(existing) => {
+            let id = existing.id;
+            let unchanged = Habit {
+                id: habit.id,
+                ..existing
+            } == *habit;
+            if unchanged {
+                counts.skipped += 1;
+            } else {
+                counts.conflicted += 1;
+            }
+            Ok(id)
+        }
+        None => {
+            transaction.execute(
+                "INSERT INTO habit (habitType, eventIds, title, question, archivedAt, kind, unit, target, schedule, removedEventIds) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    habit.habit_type,
+                    habit.event_ids,
+                    habit.title,
+                    habit.question,
+                    habit.archived_at,
+                    habit.kind,
+                    habit.unit,
+                    habit.target,
+                    habit.schedule,
+                    habit.removed_event_ids
+                ],
+            )?;
+            counts.added += 1;
+            Ok(transaction.last_insert_rowid())
+        }
+    }
+}
+
+fn import_habit_entry(
+    transaction: &Transaction,
+    entry: &HabitEntry,
+    habit_id: i64,
+    counts: &mut ImportCounts,
+) -> Result<(), HabitTrackerError> {
+    let existing = transaction
+        .query_row(
+            "SELECT * FROM habitEntry WHERE habitId = ?1 AND day = ?2",
+            params![habit_id, entry.day],
+            HabitEntry::from_row,
+        )
+        .optional()?;
+    match existing {
+        Some(existing)
+            if existing.completed == entry.completed && existing.value == entry.value =>
+        {
+            counts.skipped += 1
+        }
+        Some(_) => counts.conflicted += 1,
+        None => {
+            transaction.execute(
+                "INSERT INTO habitEntry (completed, habitId, date, day, value, timezone) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    entry.completed,
+                    habit_id,
+                    entry.date,
+                    entry.day,
+                    entry.value,
+                    entry.timezone
+                ],
+            )?;
+            counts.added += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use crate::export::{import_archive, HabitTrackerArchive, ImportMode, EXPORT_FORMAT_VERSION};
+    use crate::{get_test_app_config, HabitTrackerError, HabitTrackerService};
+    use chrono::Utc;
+    use std::error::Error;
+
+    #[test]
+    fn test_import_archive_validation() -> Result<(), Box<dyn Error>> {
+        let mut service = HabitTrackerService::build(get_test_app_config()?)?;
+        let archive = HabitTrackerArchive {
+            format_version: EXPORT_FORMAT_VERSION + 1,
+            exported_at: Utc::now(),
+            habits: vec![],
+            habit_entries: vec![],
+            calendar_events: vec![],
+        };
+        assert!(matches!(
+            import_archive(&mut service, &archive, ImportMode::Merge),
+            Err(HabitTrackerError::Validation { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_format_version_1() -> Result<(), Box<dyn Error>> {
+        let mut service = HabitTrackerService::build(get_test_app_config()?)?;
+        let archive: HabitTrackerArchive = serde_json::from_str(
+            r#"{
+                "format_version": 1,
+                "exported_at": "2025-06-01T12:00:00Z",
+                "habits": [],
+                "habit_entries": [],
+                "calendar_events": [{
+                    "id": "eventId",
+                    "name": "Gym",
+                    "start_date": "2025-06-02T05:00:00Z",
+                    "recurrence": "FREQ=WEEKLY;BYDAY=MO"
+                }]
+            }"#,
+        )?;
+        import_archive(&mut service, &archive, ImportMode::Merge)?;
+        let events = service.get_apple_calendar_events()?;
+        assert_eq!(events.len(), 1);
+        assert!(events[0].excluded_dates.values.is_empty());
+        assert_eq!(events[0].removed_at, None);
+        Ok(())
+    }
+}
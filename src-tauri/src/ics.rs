@@ -0,0 +1,490 @@
+use crate::{
+    api::{AppleCalendarEvent, CalendarSyncReport, ExcludedDates},
+    error::{FieldError, HabitTrackerError},
+    HabitTrackerService,
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::{fs, path::Path};
+
+/// Syncs the stored calendar events with the recurring events of iCalendar (RFC 5545)
+/// files, as exported by most calendar apps. This is the same sync an Apple Calendar sync
+/// does, so habits can be linked to the imported events on any platform.
+pub fn import_ics_files<P: AsRef<Path>>(
+    service: &mut HabitTrackerService,
+    paths: &[P],
+) -> Result<CalendarSyncReport, HabitTrackerError> {
+    let events = read_ics_files(paths, service.timezone)?;
+    service.sync_calendar_events(events)
+}
+
+/// Recurring events of iCalendar files, see `parse_ics`. When files contain the same event,
+/// the first file wins.
+pub fn read_ics_files<P: AsRef<Path>>(
+    paths: &[P],
+    timezone: Tz,
+) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+    let mut events: Vec<AppleCalendarEvent> = vec![];
+    for path in paths {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).map_err(|e| HabitTrackerError::CalendarUnavailable {
+                message: format!("{}: {e}", path.display()),
+            })?;
+        let parsed = parse_ics(&content, timezone).map_err(|e| match e {
+            HabitTrackerError::Validation { fields, .. } => HabitTrackerError::validation(
+                fields
+                    .into_iter()
+                    .map(|error| FieldError {
+                        field: format!("{}: {}", path.display(), error.field),
+                        message: error.message,
+                    })
+                    .collect(),
+            ),
+            e => e,
+        })?;
+        for event in parsed {
+            if !events.iter().any(|existing| existing.id == event.id) {
+                events.push(event);
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Parses the recurring events of an iCalendar file, events without an `RRULE` are skipped
+/// like the Apple Calendar sync does. An event with a `RECURRENCE-ID` moves an occurrence of
+/// its recurring event: the occurrence is excluded from it, and the event is imported as a
+/// single occurrence with the recurring event as parent, unless it was cancelled. Floating and all-day dates, and dates in a `TZID` that
+/// is not an IANA name (Outlook writes Windows zone names), are read in `timezone`.
+/// Malformed events fail with a `Validation` error naming their line.
+pub fn parse_ics(
+    content: &str,
+    timezone: Tz,
+) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+    let mut events = vec![];
+    // their recurring event may come later
+    let mut overrides = vec![];
+    let mut errors = vec![];
+    let mut event: Option<EventBuilder> = None;
+    // components nested in the current event, e.g. VALARM, whose properties are not the event's
+    let mut nesting = 0;
+    for (line_number, line) in unfold_lines(content) {
+        let Some(property) = ContentLine::parse(&line) else {
+            if event.is_some() {
+                errors.push(line_error(line_number, "Malformed line"));
+            }
+            continue;
+        };
+        match (property.name.as_str(), property.value) {
+            ("BEGIN", "VEVENT") if event.is_none() => event = Some(EventBuilder::new(line_number)),
+            ("BEGIN", _) if event.is_some() => nesting += 1,
+            ("END", _) if nesting > 0 => nesting -= 1,
+            ("END", "VEVENT") => {
+                let Some(builder) = event.take() else {
+                    continue;
+                };
+                if builder.recurrence_id.is_some() {
+                    overrides.push(builder);
+                    continue;
+                }
+                match builder.build() {
+                    Ok(Some(built)) => events.push(built),
+                    Ok(None) => {}
+                    Err(error) => errors.push(error),
+                }
+            }
+            _ if nesting > 0 => {}
+            _ => {
+                if let Some(builder) = &mut event {
+                    if let Err(message) = builder.set(&property, timezone) {
+                        errors.push(line_error(line_number, message));
+                        builder.invalid = true;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(builder) = event {
+        errors.push(line_error(builder.line_number, "Unterminated VEVENT"));
+    }
+    let mut moved_occurrences = vec![];
+    for builder in overrides {
+        let (Some(parent_id), Some(recurrence_id)) = (builder.id, builder.recurrence_id) else {
+            continue;
+        };
+        let Some(parent) = events.iter_mut().find(|event| event.id == parent_id) else {
+            continue;
+        };
+        let excluded_dates = &mut parent.excluded_dates.values;
+        excluded_dates.push(recurrence_id);
+        excluded_dates.sort();
+        excluded_dates.dedup();
+        if builder.cancelled || builder.invalid {
+            continue;
+        }
+        moved_occurrences.push(AppleCalendarEvent {
+            // the UID is shared with the parent, the original start tells occurrences apart
+            id: format!("{parent_id}/{}", recurrence_id.format("%Y%m%dT%H%M%SZ")),
+            name: match builder.name.is_empty() {
+                true => parent.name.clone(),
+                false => builder.name,
+            },
+            start_date: builder.start_date.unwrap_or(recurrence_id),
+            recurrence: SINGLE_OCCURRENCE.to_string(),
+            excluded_dates: ExcludedDates::default(),
+            parent_id: Some(parent_id),
+            recurrence_rule: None,
+            recurrence_error: None,
+            removed_at: None,
+        });
+    }
+    events.extend(moved_occurrences);
+    if errors.is_empty() {
+        Ok(events)
+    } else {
+        Err(HabitTrackerError::validation(errors))
+    }
+}
+
+// the recurrence of events moving a single occurrence of another one
+const SINGLE_OCCURRENCE: &str = "FREQ=DAILY;COUNT=1";
+
+fn line_error(line_number: usize, message: impl Into<String>) -> FieldError {
+    FieldError {
+        field: format!("line {line_number}"),
+        message: message.into(),
+    }
+}
+
+// Long lines are folded onto continuation lines starting with a space or tab. Yields each
+// unfolded line with the number of the line it starts on.
+fn unfold_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+    for (index, line) in content.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, unfolded))) => unfolded.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+// `NAME;PARAM=VALUE;...:VALUE`, parameter values may be quoted and contain `;` or `:`.
+struct ContentLine<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> ContentLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let mut quoted = false;
+        let mut segments = vec![];
+        let mut segment_start = 0;
+        for (index, char) in line.char_indices() {
+            match char {
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    segments.push(&line[segment_start..index]);
+                    segment_start = index + 1;
+                }
+                ':' if !quoted => {
+                    segments.push(&line[segment_start..index]);
+                    let (name, params) = segments.split_first()?;
+                    if name.is_empty() {
+                        return None;
+                    }
+                    let params = params
+                        .iter()
+                        .map(|param| {
+                            let (key, value) = param.split_once('=')?;
+                            Some((key.to_ascii_uppercase(), value.trim_matches('"')))
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    return Some(ContentLine {
+                        name: name.to_ascii_uppercase(),
+                        params,
+                        value: &line[index + 1..],
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn param(&self, key: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| *value)
+    }
+}
+
+struct EventBuilder {
+    line_number: usize,
+    id: Option<String>,
+    name: String,
+    start_date: Option<DateTime<Utc>>,
+    recurrence: Option<String>,
+    excluded_dates: Vec<DateTime<Utc>>,
+    // the original start of the occurrence of another event this one overrides
+    recurrence_id: Option<DateTime<Utc>>,
+    cancelled: bool,
+    // a property failed to parse and was reported already
+    invalid: bool,
+}
+
+impl EventBuilder {
+    fn new(line_number: usize) -> Self {
+        EventBuilder {
+            line_number,
+            id: None,
+            name: String::new(),
+            start_date: None,
+            recurrence: None,
+            excluded_dates: vec![],
+            recurrence_id: None,
+            cancelled: false,
+            invalid: false,
+        }
+    }
+
+    fn set(&mut self, property: &ContentLine, timezone: Tz) -> Result<(), &'static str> {
+        match property.name.as_str() {
+            "UID" => self.id = Some(property.value.to_string()),
+            "SUMMARY" => self.name = unescape_text(property.value),
+            "DTSTART" => {
+                self.start_date = Some(parse_date_time(property, property.value, timezone)?);
+            }
+            "RRULE" => self.recurrence = Some(property.value.to_string()),
+            "EXDATE" => {
+                for value in property.value.split(',') {
+                    self.excluded_dates
+                        .push(parse_date_time(property, value, timezone)?);
+                }
+            }
+            "STATUS" => self.cancelled = property.value.eq_ignore_ascii_case("CANCELLED"),
+            "RECURRENCE-ID" => {
+                self.recurrence_id = Some(parse_date_time(property, property.value, timezone)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<Option<AppleCalendarEvent>, FieldError> {
+        let Some(recurrence) = self.recurrence.filter(|_| !self.invalid) else {
+            return Ok(None);
+        };
+        let id = self
+            .id
+            .ok_or_else(|| line_error(self.line_number, "Missing UID"))?;
+        let start_date = self
+            .start_date
+            .ok_or_else(|| line_error(self.line_number, "Missing DTSTART"))?;
+        Ok(Some(AppleCalendarEvent {
+            id,
+            name: self.name,
+            start_date,
+            recurrence,
+            excluded_dates: ExcludedDates {
+                values: self.excluded_dates,
+            },
+            parent_id: None,
+            recurrence_rule: None,
+            recurrence_error: None,
+            removed_at: None,
+        }))
+    }
+}
+
+fn parse_date_time(
+    property: &ContentLine,
+    value: &str,
+    timezone: Tz,
+) -> Result<DateTime<Utc>, &'static str> {
+    let invalid = match property.name.as_str() {
+        "EXDATE" => "Invalid EXDATE",
+        "RECURRENCE-ID" => "Invalid RECURRENCE-ID",
+        _ => "Invalid DTSTART",
+    };
+    if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|date_time| date_time.and_utc())
+            .map_err(|_| invalid);
+    }
+    let local = match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(date) => date.and_time(Default::default()),
+        Err(_) => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid)?,
+    };
+    let timezone = property
+        .param("TZID")
+        .and_then(|tzid| tzid.trim_start_matches('/').parse::<Tz>().ok())
+        .unwrap_or(timezone);
+    // local times skipped by a DST transition are moved past it
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .ok_or(invalid)
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            text.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use crate::error::HabitTrackerError;
+    use crate::ics::parse_ics;
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Example//Calendar//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:gym@example.com\r
+SUMMARY:Gym\\, upper body\r
+DTSTART;TZID=Europe/Berlin:20250602T070000\r
+RRULE:FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE\r
+EXDATE;TZID=Europe/Berlin:20250609T070000,20250611T070000\r
+EXDATE;TZID=Europe/Berlin:20250616T070000\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+DESCRIPTION:Reminder\r
+TRIGGER:-PT15M\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:gym@example.com\r
+RECURRENCE-ID;TZID=Europe/Berlin:20250604T070000\r
+SUMMARY:Gym\\, moved\r
+DTSTART;TZID=Europe/Berlin:20250605T180000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:gym@example.com\r
+RECURRENCE-ID;TZID=Europe/Berlin:20250618T070000\r
+STATUS:CANCELLED\r
+DTSTART;TZID=Europe/Berlin:20250618T070000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:plants@example.com\r
+SUMMARY:Water the plants on the balcony and in the living room, and remember the\r
+  herbs\r
+DTSTART;VALUE=DATE:20250601\r
+RRULE:FREQ=DAILY;INTERVAL=3;UNTIL=20251231T000000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:dentist@example.com\r
+SUMMARY:Dentist\r
+DTSTART:20250610T080000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_parse_ics() -> Result<(), HabitTrackerError> {
+        let events = parse_ics(CALENDAR, Tz::America__New_York)?;
+        // the single event is skipped, the moved occurrence comes last
+        assert_eq!(events.len(), 3);
+
+        let gym = &events[0];
+        assert_eq!(gym.id, "gym@example.com");
+        assert_eq!(gym.name, "Gym, upper body");
+        assert_eq!(
+            gym.start_date,
+            Utc.with_ymd_and_hms(2025, 6, 2, 5, 0, 0).unwrap()
+        );
+        assert_eq!(gym.recurrence, "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE");
+        // moved and cancelled occurrences are excluded
+        assert_eq!(
+            gym.excluded_dates.values,
+            vec![
+                Utc.with_ymd_and_hms(2025, 6, 4, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 9, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 11, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 16, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 18, 5, 0, 0).unwrap(),
+            ]
+        );
+        assert_eq!(gym.parent_id, None);
+
+        let moved = &events[2];
+        assert_eq!(moved.id, "gym@example.com/20250604T050000Z");
+        assert_eq!(moved.name, "Gym, moved");
+        assert_eq!(
+            moved.start_date,
+            Utc.with_ymd_and_hms(2025, 6, 5, 16, 0, 0).unwrap()
+        );
+        assert_eq!(moved.recurrence, "FREQ=DAILY;COUNT=1");
+        assert_eq!(moved.parent_id.as_deref(), Some("gym@example.com"));
+
+        let plants = &events[1];
+        assert_eq!(
+            plants.name,
+            "Water the plants on the balcony and in the living room, and remember the herbs"
+        );
+        // all-day events start at midnight in the configured timezone
+        assert_eq!(
+            plants.start_date,
+            Utc.with_ymd_and_hms(2025, 6, 1, 4, 0, 0).unwrap()
+        );
+        assert!(plants.excluded_dates.values.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ics_errors() {
+        let calendar = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:No uid
+DTSTART:20250602T070000Z
+RRULE:FREQ=DAILY
+END:VEVENT
+BEGIN:VEVENT
+UID:bad-start
+DTSTART:2025-06-02
+RRULE:FREQ=DAILY
+END:VEVENT
+END:VCALENDAR
+";
+        match parse_ics(calendar, Tz::UTC) {
+            Err(HabitTrackerError::Validation { fields, .. }) => {
+                let fields = fields
+                    .iter()
+                    .map(|error| (error.field.as_str(), error.message.as_str()))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    fields,
+                    vec![("line 2", "Missing UID"), ("line 9", "Invalid DTSTART"),]
+                );
+            }
+            other => panic!("Expected Validation error, got {other:?}"),
+        }
+    }
+}
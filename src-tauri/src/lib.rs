@@ -1,126 +1,345 @@
 pub mod api;
 pub mod app_config;
+pub mod backup;
+pub mod calendar_source;
+pub mod csv_export;
+pub mod doctor;
+pub mod error;
+pub mod export;
+pub mod ics;
+pub mod migrations;
+pub mod recurrence;
 pub mod requests;
+pub mod service_pool;
+pub mod validation;
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-use crate::api::AppleCalendarEvent;
-use crate::api::HabitEntry;
+use crate::api::{
+    AppleCalendarEvent, CalendarSyncReport, HabitEntry, HabitStats, HabitStreak, StatsGranularity,
+};
 pub use api::{Habit, HabitTrackerService};
+use app_config::{config_file_path, read_config_file, write_config_file, ConfigFile};
 pub use app_config::{get_app_config, get_test_app_config, AppConfig};
+use backup::BackupInfo;
+use calendar_source::{build_calendar_source, CalendarSourceConfig};
+use chrono::NaiveDate;
+use csv_export::CsvLayout;
+use doctor::DoctorReport;
+pub use error::{FieldError, HabitTrackerError};
+use export::{HabitTrackerArchive, ImportMode, ImportReport};
 pub use requests::{
-    CreateHabitRequest, InsertHabitEntriesRequest, InsertHabitEntryItem, UpdateHabitRequest,
+    CreateHabitRequest, DateRange, InsertHabitEntriesRequest, InsertHabitEntryItem,
+    UpdateHabitEntryRequest, UpdateHabitRequest,
 };
+use service_pool::{with_service, ServicePool};
 #[cfg(debug_assertions)]
 use specta::{
-    export,
+    export::ts_with_cfg,
     ts::{BigIntExportBehavior, ExportConfiguration},
 };
-use std::{error::Error, sync::Mutex};
+use std::{error::Error, path::PathBuf};
 use tauri::State;
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-use tokio::process::Command;
+
+// Services are synchronous, so commands run them on blocking threads, each with a service
+// of its own from the pool. A command that panics fails alone, there is no lock to poison.
+async fn run_blocking<T: Send + 'static>(
+    pool: &ServicePool,
+    f: impl FnOnce(&mut HabitTrackerService) -> Result<T, HabitTrackerError> + Send + 'static,
+) -> Result<T, HabitTrackerError> {
+    let pool = pool.clone();
+    tauri::async_runtime::spawn_blocking(move || with_service(&pool, f))
+        .await
+        .map_err(|e| HabitTrackerError::Storage {
+            message: e.to_string(),
+        })?
+}
+
+#[tauri::command]
+async fn get_habits(
+    pool: State<'_, ServicePool>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Habit>, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.get_habits(include_archived.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_due_habits(
+    pool: State<'_, ServicePool>,
+    day: NaiveDate,
+) -> Result<Vec<Habit>, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.get_due_habits(day)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_habit_entries(
+    pool: State<'_, ServicePool>,
+) -> Result<Vec<HabitEntry>, HabitTrackerError> {
+    run_blocking(&pool, |habit_tracker_service| {
+        habit_tracker_service.get_habit_entries()
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_habit_streaks(
+    pool: State<'_, ServicePool>,
+) -> Result<Vec<HabitStreak>, HabitTrackerError> {
+    run_blocking(&pool, |habit_tracker_service| {
+        habit_tracker_service.get_habit_streaks()
+    })
+    .await
+}
 
 #[tauri::command]
-fn get_habits(state: State<Mutex<HabitTrackerService>>) -> Result<Vec<Habit>, String> {
-    let habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .get_habits()
-        .map_err(|e| e.to_string())
+async fn get_habit_stats(
+    pool: State<'_, ServicePool>,
+    habit_id: i64,
+    range: DateRange,
+    granularity: StatsGranularity,
+) -> Result<HabitStats, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.get_habit_stats(habit_id, range, granularity)
+    })
+    .await
 }
 
 #[tauri::command]
-fn get_habit_entries(state: State<Mutex<HabitTrackerService>>) -> Result<Vec<HabitEntry>, String> {
-    let habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .get_habit_entries()
-        .map_err(|e| e.to_string())
+async fn get_due_dates(
+    pool: State<'_, ServicePool>,
+    habit_id: i64,
+    range: DateRange,
+) -> Result<Vec<NaiveDate>, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.get_due_dates(habit_id, range)
+    })
+    .await
 }
 
 #[tauri::command]
-fn create_habit(
-    state: State<Mutex<HabitTrackerService>>,
+async fn create_habit(
+    pool: State<'_, ServicePool>,
     request: CreateHabitRequest,
-) -> Result<Habit, String> {
-    let habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .create_habit(request)
-        .map_err(|e| e.to_string())
+) -> Result<Habit, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.create_habit(request)
+    })
+    .await
 }
 
 #[tauri::command]
-fn update_habit(
-    state: State<Mutex<HabitTrackerService>>,
+async fn update_habit(
+    pool: State<'_, ServicePool>,
     request: UpdateHabitRequest,
-) -> Result<Habit, String> {
-    let habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .update_habit(request)
-        .map_err(|e| e.to_string())
+) -> Result<Habit, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.update_habit(request)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn archive_habit(pool: State<'_, ServicePool>, id: i64) -> Result<Habit, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.archive_habit(id)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn unarchive_habit(
+    pool: State<'_, ServicePool>,
+    id: i64,
+) -> Result<Habit, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.unarchive_habit(id)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn delete_habit(pool: State<'_, ServicePool>, id: i64) -> Result<(), HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.delete_habit(id)
+    })
+    .await
 }
 
 #[tauri::command]
-fn insert_habit_entries(
-    state: State<Mutex<HabitTrackerService>>,
+async fn insert_habit_entries(
+    pool: State<'_, ServicePool>,
     request: InsertHabitEntriesRequest,
-) -> Result<Vec<HabitEntry>, String> {
-    let mut habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .insert_habit_entries(request)
-        .map_err(|e| e.to_string())
+) -> Result<Vec<HabitEntry>, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.insert_habit_entries(request)
+    })
+    .await
 }
 
 #[tauri::command]
-fn get_apple_calendar_feature_status() -> bool {
-    cfg!(target_os = "macos") && cfg!(feature = "apple_calendar")
+async fn update_habit_entry(
+    pool: State<'_, ServicePool>,
+    request: UpdateHabitEntryRequest,
+) -> Result<HabitEntry, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.update_habit_entry(request)
+    })
+    .await
 }
 
 #[tauri::command]
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-fn get_apple_calendar_events(
-    state: State<Mutex<HabitTrackerService>>,
-) -> Result<Vec<AppleCalendarEvent>, String> {
-    let habit_tracker_service = state.lock().unwrap();
-    habit_tracker_service
-        .get_apple_calendar_events()
-        .map_err(|e| e.to_string())
+async fn delete_habit_entry(
+    pool: State<'_, ServicePool>,
+    id: i64,
+) -> Result<(), HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.delete_habit_entry(id)
+    })
+    .await
 }
 
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-pub async fn sync_apple_calendar_events_impl(
-    service: &Mutex<HabitTrackerService>,
-) -> Result<Vec<AppleCalendarEvent>, String> {
-    // Embed the AppleScript content in the binary
-    let applescript_content =
-        include_str!("../../scripts/get_recurring_calendar_events.applescript");
+#[tauri::command]
+async fn export_data(
+    pool: State<'_, ServicePool>,
+) -> Result<HabitTrackerArchive, HabitTrackerError> {
+    run_blocking(&pool, |habit_tracker_service| {
+        export::export_archive(habit_tracker_service)
+    })
+    .await
+}
 
-    let output_utf8 = Command::new("osascript")
-        .args(["-e", applescript_content])
-        .output()
+#[tauri::command]
+async fn import_data(
+    pool: State<'_, ServicePool>,
+    archive: HabitTrackerArchive,
+    mode: ImportMode,
+) -> Result<ImportReport, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        export::import_archive(habit_tracker_service, &archive, mode)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_csv(
+    pool: State<'_, ServicePool>,
+    path: PathBuf,
+    layout: CsvLayout,
+    range: Option<DateRange>,
+) -> Result<(), HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        let file = std::fs::File::create(&path)?;
+        csv_export::export_csv(
+            habit_tracker_service,
+            std::io::BufWriter::new(file),
+            layout,
+            range,
+        )
+    })
+    .await
+}
+
+// whether calendar syncs can run, Apple Calendar is only available on macOS
+#[tauri::command]
+fn get_apple_calendar_feature_status(calendar_source: State<CalendarSourceConfig>) -> bool {
+    calendar_source.is_available()
+}
+
+#[tauri::command]
+async fn get_apple_calendar_events(
+    pool: State<'_, ServicePool>,
+) -> Result<Vec<AppleCalendarEvent>, HabitTrackerError> {
+    run_blocking(&pool, |habit_tracker_service| {
+        habit_tracker_service.get_apple_calendar_events()
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_ics_calendar(
+    pool: State<'_, ServicePool>,
+    paths: Vec<PathBuf>,
+) -> Result<CalendarSyncReport, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        ics::import_ics_files(habit_tracker_service, &paths)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn sync_calendar_events(
+    pool: State<'_, ServicePool>,
+    calendar_source: State<'_, CalendarSourceConfig>,
+) -> Result<CalendarSyncReport, HabitTrackerError> {
+    let timezone = run_blocking(&pool, |habit_tracker_service| {
+        Ok(habit_tracker_service.timezone)
+    })
+    .await?;
+    let source = build_calendar_source(&calendar_source, timezone);
+    // reading the calendar can take seconds, keep it off the async runtime and the pool
+    let events = tauri::async_runtime::spawn_blocking(move || source.events())
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| HabitTrackerError::CalendarUnavailable {
+            message: e.to_string(),
+        })??;
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.sync_calendar_events(events)
+    })
+    .await
+}
 
-    let request = serde_json::from_slice::<Vec<AppleCalendarEvent>>(&output_utf8.stdout)
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+fn get_config() -> Result<ConfigFile, HabitTrackerError> {
+    read_config_file(&config_file_path()?)
+}
 
-    let mut habit_tracker_service = service.lock().unwrap();
-    habit_tracker_service
-        .reset_apple_calendar_events(request)
-        .map_err(|e| e.to_string())
+// the new config takes effect on the next start
+#[tauri::command]
+fn update_config(config: ConfigFile) -> Result<ConfigFile, HabitTrackerError> {
+    write_config_file(&config_file_path()?, &config)?;
+    Ok(config)
 }
 
 #[tauri::command]
-#[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-async fn sync_apple_calendar_events(
-    state: State<'_, Mutex<HabitTrackerService>>,
-) -> Result<Vec<AppleCalendarEvent>, String> {
-    sync_apple_calendar_events_impl(&state).await
+async fn list_backups(pool: State<'_, ServicePool>) -> Result<Vec<BackupInfo>, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        backup::list_backups(habit_tracker_service)
+    })
+    .await
+}
+
+// returns the backup of the replaced data
+#[tauri::command]
+async fn restore_backup(
+    pool: State<'_, ServicePool>,
+    name: String,
+) -> Result<BackupInfo, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        backup::restore_backup(habit_tracker_service, &name)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn doctor(
+    pool: State<'_, ServicePool>,
+    fix: Option<bool>,
+) -> Result<DoctorReport, HabitTrackerError> {
+    run_blocking(&pool, move |habit_tracker_service| {
+        habit_tracker_service.doctor(fix.unwrap_or(false))
+    })
+    .await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() -> Result<(), Box<dyn Error>> {
     #[cfg(debug_assertions)]
     {
-        export::ts_with_cfg(
+        ts_with_cfg(
             "../src/api/dtos.ts",
             &ExportConfiguration::default().bigint(BigIntExportBehavior::Number),
         )
@@ -128,22 +347,47 @@ pub fn run() -> Result<(), Box<dyn Error>> {
     }
 
     let app_config = get_app_config()?;
-    let habit_tracker_service = HabitTrackerService::build(app_config)?;
+    let calendar_source = app_config.calendar_source.clone();
+    // migrated and backed up once, before the pool opens more connections
+    let habit_tracker_service = HabitTrackerService::build(app_config.clone())?;
+    // a failed backup should not keep the app from starting
+    if let Err(e) = backup::create_backup(&habit_tracker_service, backup::BackupReason::Start) {
+        eprintln!("Could not back up the database: {e}");
+    }
+    drop(habit_tracker_service);
+    let pool = service_pool::build_service_pool(app_config)?;
 
     tauri::Builder::default()
-        .manage(Mutex::new(habit_tracker_service))
+        .manage(pool)
+        .manage(calendar_source)
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             get_habits,
+            get_due_habits,
             get_habit_entries,
+            get_habit_streaks,
+            get_habit_stats,
+            get_due_dates,
             create_habit,
             update_habit,
+            archive_habit,
+            unarchive_habit,
+            delete_habit,
             insert_habit_entries,
+            update_habit_entry,
+            delete_habit_entry,
+            export_data,
+            import_data,
+            export_csv,
             get_apple_calendar_feature_status,
-            #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
             get_apple_calendar_events,
-            #[cfg(all(target_os = "macos", feature = "apple_calendar"))]
-            sync_apple_calendar_events,
+            import_ics_calendar,
+            sync_calendar_events,
+            get_config,
+            update_config,
+            list_backups,
+            restore_backup,
+            doctor,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,333 @@
+use rusqlite::{Connection, Transaction};
+use std::{error::Error, fmt};
+
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+// Ordered list of schema migrations. `PRAGMA user_version` stores how many of them
+// have been applied, so migrations must only ever be appended, never reordered or edited.
+const MIGRATIONS: &[Migration] = &[
+    initial_schema,
+    add_habit_archived_at,
+    add_habit_entry_day,
+    add_measurable_habits,
+    add_habit_schedule,
+    add_habit_entry_timezone,
+    add_calendar_event_excluded_dates,
+    add_calendar_event_recurrence_rule,
+    add_calendar_event_tombstones,
+    add_calendar_event_parent,
+    reset_calendar_event_recurrence_rules,
+];
+
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    UnsupportedVersion { found: u32, supported: u32 },
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "database schema version {found} is newer than the latest supported version {supported}"
+            ),
+            MigrationError::Sqlite(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::UnsupportedVersion { .. } => None,
+            MigrationError::Sqlite(e) => Some(e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+pub fn migrate(conn: &mut Connection) -> Result<(), MigrationError> {
+    let version = schema_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(MigrationError::UnsupportedVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = conn.transaction()?;
+        // another connection may have run it meanwhile, e.g. `habit-sync` starting with the app
+        if schema_version(&transaction)? > index as u32 {
+            continue;
+        }
+        migration(&transaction)?;
+        transaction.pragma_update(None, "user_version", index as u32 + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+// Databases created before migrations existed already contain these tables at
+// user_version 0, hence `IF NOT EXISTS`.
+fn initial_schema(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS habit (
+            id INTEGER PRIMARY KEY,
+            habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+            eventIds TEXT NULL,
+            title TEXT NOT NULL,
+            question TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS habitEntry (
+            id INTEGER PRIMARY KEY,
+            completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+            date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            habitId INTEGER,
+            FOREIGN KEY(habitId) REFERENCES habit(id)
+        );
+        CREATE TABLE IF NOT EXISTS appleCalendarEvent (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            startDate REAL NOT NULL,
+            endDate REAL NULL,
+            recurrence TEXT NOT NULL
+        );",
+    )
+}
+
+fn add_habit_archived_at(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch("ALTER TABLE habit ADD COLUMN archivedAt REAL NULL")
+}
+
+// Entries become unique per habit and local day. Databases may already contain several
+// check-ins for the same day, in which case only the latest one is kept.
+fn add_habit_entry_day(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE habitEntry ADD COLUMN day TEXT NOT NULL DEFAULT '';
+        UPDATE habitEntry SET day = date(date, 'localtime');
+        DELETE FROM habitEntry WHERE id NOT IN (
+            SELECT MAX(id) FROM habitEntry GROUP BY habitId, day
+        );
+        CREATE UNIQUE INDEX habitEntry_habitId_day ON habitEntry(habitId, day);",
+    )
+}
+
+fn add_measurable_habits(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE habit ADD COLUMN kind TEXT CHECK(kind IN('yesNo', 'measurable')) NOT NULL DEFAULT 'yesNo';
+        ALTER TABLE habit ADD COLUMN unit TEXT NULL;
+        ALTER TABLE habit ADD COLUMN target REAL NULL;
+        ALTER TABLE habitEntry ADD COLUMN value REAL NULL;",
+    )
+}
+
+// NULL means the habit is due every day
+fn add_habit_schedule(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch("ALTER TABLE habit ADD COLUMN schedule TEXT NULL")
+}
+
+// NULL for entries recorded before timezones were stored
+fn add_habit_entry_timezone(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch("ALTER TABLE habitEntry ADD COLUMN timezone TEXT NULL")
+}
+
+// JSON array of RFC 3339 dates, NULL meaning none.
+fn add_calendar_event_excluded_dates(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch("ALTER TABLE appleCalendarEvent ADD COLUMN excludedDates TEXT NULL")
+}
+
+// JSON of the parsed `recurrence`, or why it could not be parsed.
+// Left NULL here, `derive_recurrence_rules` fills them in with the current parser when the
+// database is opened, so this migration doesn't change along with the parser.
+fn add_calendar_event_recurrence_rule(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE appleCalendarEvent ADD COLUMN recurrenceRule TEXT NULL;
+        ALTER TABLE appleCalendarEvent ADD COLUMN recurrenceError TEXT NULL;",
+    )
+}
+
+// Events missing from a sync keep their row with `removedAt` set, habits linked to them
+// list them in `removedEventIds` (JSON like `eventIds`, NULL meaning none).
+fn add_calendar_event_tombstones(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE appleCalendarEvent ADD COLUMN removedAt REAL NULL;
+        ALTER TABLE habit ADD COLUMN removedEventIds TEXT NULL;",
+    )
+}
+
+// Events moving a single occurrence of a recurring event point to it with `parentId`.
+fn add_calendar_event_parent(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch("ALTER TABLE appleCalendarEvent ADD COLUMN parentId TEXT NULL")
+}
+
+// Rules stored a date-only `UNTIL` as the end of the day in UTC. Cleared for
+// `derive_recurrence_rules` to parse them again, keeping such dates as dates.
+fn reset_calendar_event_recurrence_rules(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "UPDATE appleCalendarEvent SET recurrenceRule = NULL, recurrenceError = NULL",
+    )
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use crate::{
+        api::derive_recurrence_rules,
+        migrations::{migrate, schema_version, MigrationError, SCHEMA_VERSION},
+    };
+    use rusqlite::{Connection, Result};
+    use std::error::Error;
+
+    // Snapshots of databases as written by every past schema version, with sample data.
+    const FIXTURES: &[(u32, &str)] = &[
+        (0, include_str!("../tests/fixtures/schema_v0.sql")),
+        (0, include_str!("../tests/fixtures/schema_v0_basic.sql")),
+        (1, include_str!("../tests/fixtures/schema_v1.sql")),
+        (2, include_str!("../tests/fixtures/schema_v2.sql")),
+        (3, include_str!("../tests/fixtures/schema_v3.sql")),
+        (4, include_str!("../tests/fixtures/schema_v4.sql")),
+        (5, include_str!("../tests/fixtures/schema_v5.sql")),
+        (6, include_str!("../tests/fixtures/schema_v6.sql")),
+        (7, include_str!("../tests/fixtures/schema_v7.sql")),
+        (8, include_str!("../tests/fixtures/schema_v8.sql")),
+        (9, include_str!("../tests/fixtures/schema_v9.sql")),
+        (10, include_str!("../tests/fixtures/schema_v10.sql")),
+    ];
+
+    fn count_rows(conn: &Connection, table: &str) -> Result<i64> {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })
+    }
+
+    fn count_habit_entry_days(conn: &Connection) -> Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(DISTINCT habitId || ':' || date(date, 'localtime')) FROM habitEntry",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    #[test]
+    fn test_migrate_new_database() -> Result<(), Box<dyn Error>> {
+        let mut conn = Connection::open_in_memory()?;
+        migrate(&mut conn)?;
+        assert_eq!(schema_version(&conn)?, SCHEMA_VERSION);
+        // running again is a no-op
+        migrate(&mut conn)?;
+        assert_eq!(schema_version(&conn)?, SCHEMA_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_fixtures() -> Result<(), Box<dyn Error>> {
+        for (version, fixture) in FIXTURES {
+            let mut conn = Connection::open_in_memory()?;
+            conn.execute_batch(fixture)?;
+            assert_eq!(schema_version(&conn)?, *version);
+            let habit_count = count_rows(&conn, "habit")?;
+            let habit_entry_day_count = count_habit_entry_days(&conn)?;
+
+            migrate(&mut conn)?;
+
+            assert_eq!(schema_version(&conn)?, SCHEMA_VERSION);
+            assert_eq!(count_rows(&conn, "habit")?, habit_count);
+            assert_eq!(count_rows(&conn, "habitEntry")?, habit_entry_day_count);
+            count_rows(&conn, "appleCalendarEvent")?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_deduplicates_habit_entries() -> Result<(), Box<dyn Error>> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(include_str!("../tests/fixtures/schema_v2.sql"))?;
+        assert_eq!(count_rows(&conn, "habitEntry")?, 4);
+
+        migrate(&mut conn)?;
+
+        let mut statement =
+            conn.prepare("SELECT id, completed FROM habitEntry WHERE habitId = 1 ORDER BY day")?;
+        let entries = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(i64, bool)>>>()?;
+        // the latest check-in of the duplicated day wins
+        assert_eq!(entries, vec![(1, true), (3, true)]);
+        let duplicate = conn.execute(
+            "INSERT INTO habitEntry (completed, date, habitId, day) VALUES (1, '2025-03-05 18:00:00', 1, (SELECT day FROM habitEntry WHERE id = 3))",
+            [],
+        );
+        assert!(duplicate.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated_calendar_events_get_recurrence_rules() -> Result<(), Box<dyn Error>> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(include_str!("../tests/fixtures/schema_v7.sql"))?;
+        let read_events = |conn: &Connection| {
+            conn.prepare(
+                "SELECT recurrenceRule IS NOT NULL, recurrenceError FROM appleCalendarEvent ORDER BY id",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(bool, Option<String>)>>>()
+        };
+
+        // parsed once the database is opened, not by the migration
+        migrate(&mut conn)?;
+        assert_eq!(read_events(&conn)?, vec![(false, None), (false, None)]);
+
+        derive_recurrence_rules(&conn)?;
+        assert_eq!(
+            read_events(&conn)?,
+            vec![
+                (true, None),
+                (false, Some("Unsupported frequency HOURLY".to_string()))
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_reparses_date_only_until() -> Result<(), Box<dyn Error>> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(include_str!("../tests/fixtures/schema_v10.sql"))?;
+
+        migrate(&mut conn)?;
+        derive_recurrence_rules(&conn)?;
+
+        let until: Option<String> = conn.query_row(
+            "SELECT json_extract(recurrenceRule, '$.until') FROM appleCalendarEvent WHERE id = 'event-3'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(until.as_deref(), Some("2025-12-31"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_refuses_newer_database() -> Result<(), Box<dyn Error>> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)?;
+        match migrate(&mut conn) {
+            Err(MigrationError::UnsupportedVersion { found, supported }) => {
+                assert_eq!((found, supported), (SCHEMA_VERSION + 1, SCHEMA_VERSION));
+            }
+            other => panic!("Expected UnsupportedVersion error, got {other:?}"),
+        }
+        // the database is left untouched
+        assert_eq!(schema_version(&conn)?, SCHEMA_VERSION + 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,697 @@
+use crate::api::DayOfWeek;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc, Weekday};
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    Error, Result, ToSql,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{collections::HashSet, fmt::Display, ops::RangeInclusive, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` entry, e.g. `MO`, or `2TU` and `-1FR` for the second Tuesday and the last
+/// Friday of the month, or of the year for yearly rules.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+pub struct ByDay {
+    pub ordinal: Option<i32>,
+    pub day: DayOfWeek,
+}
+
+/// The end of a recurrence. Dates are floating, they end with the day in whatever timezone
+/// the app runs in, while date-times are read as UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Type)]
+#[serde(untagged)]
+pub enum Until {
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>),
+}
+
+impl Until {
+    /// The last day occurrences may fall on, `local_day` mapping date-times to days.
+    pub fn day(&self, local_day: impl Fn(DateTime<Utc>) -> NaiveDate) -> NaiveDate {
+        match self {
+            Until::Date(date) => *date,
+            Until::DateTime(date_time) => local_day(*date_time),
+        }
+    }
+}
+
+/// A recurrence rule (RFC 5545 `RRULE`) as Calendar.app and iCalendar files write them,
+/// e.g. `FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE`. Rules using parts not listed here, or
+/// sub-daily frequencies, fail to parse rather than being half understood.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Type)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+    pub week_start: DayOfWeek,
+}
+
+impl Recurrence {
+    /// Days the rule occurs on when its first occurrence is on `start`, up to and including
+    /// `end`. `until` is the day of `self.until`. Occurrences are computed per day, so
+    /// the time of day of the first occurrence doesn't matter.
+    pub fn occurrence_days(
+        &self,
+        start: NaiveDate,
+        until: Option<NaiveDate>,
+        end: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        let end = until.map_or(end, |until| until.min(end));
+        let mut days = vec![];
+        let mut index = 0;
+        while let Some((period_start, candidates)) = self.period(start, index) {
+            if period_start > end {
+                break;
+            }
+            for day in candidates.into_iter().filter(|day| *day >= start) {
+                // occurrences excluded later on still count towards COUNT
+                if day > end || self.count.is_some_and(|count| days.len() >= count as usize) {
+                    return days;
+                }
+                days.push(day);
+            }
+            index += self.interval.max(1);
+        }
+        days
+    }
+
+    // First day and occurrences of the `index`th day, week, month or year from `start`'s,
+    // None once out of the supported date range.
+    fn period(&self, start: NaiveDate, index: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let (period_start, mut candidates) = match self.frequency {
+            Frequency::Daily => {
+                let day = start.checked_add_signed(Duration::days(index.into()))?;
+                (day, self.filter(&[day], |_| true))
+            }
+            Frequency::Weekly => {
+                let week_start: Weekday = self.week_start.into();
+                let offset = (start.weekday().num_days_from_monday() + 7
+                    - week_start.num_days_from_monday())
+                    % 7;
+                let first = start
+                    .checked_add_signed(Duration::days(7 * i64::from(index) - i64::from(offset)))?;
+                let week = first.iter_days().take(7).collect::<Vec<_>>();
+                (
+                    first,
+                    self.filter(&week, |day| {
+                        !self.by_day.is_empty() || day.weekday() == start.weekday()
+                    }),
+                )
+            }
+            Frequency::Monthly => {
+                let first = start.with_day(1)?.checked_add_months(Months::new(index))?;
+                (first, self.month_candidates(start, first))
+            }
+            Frequency::Yearly => {
+                let first =
+                    NaiveDate::from_ymd_opt(start.year() + i32::try_from(index).ok()?, 1, 1)?;
+                let candidates = if self.by_month.is_empty() {
+                    let year = first
+                        .iter_days()
+                        .take_while(|day| day.year() == first.year())
+                        .collect::<Vec<_>>();
+                    self.filter(&year, |day| {
+                        !(self.by_day.is_empty() && self.by_month_day.is_empty())
+                            || (day.month(), day.day()) == (start.month(), start.day())
+                    })
+                } else {
+                    let mut months = self.by_month.clone();
+                    months.sort();
+                    months.dedup();
+                    months
+                        .into_iter()
+                        .filter_map(|month| first.with_month(month))
+                        .flat_map(|month| self.month_candidates(start, month))
+                        .collect()
+                };
+                (first, candidates)
+            }
+        };
+        if !self.by_set_pos.is_empty() {
+            candidates = select_positions(&candidates, &self.by_set_pos);
+        }
+        Some((period_start, candidates))
+    }
+
+    // Occurrences within the month starting on `first`. By default on `start`'s day of the
+    // month, months without that day are skipped.
+    fn month_candidates(&self, start: NaiveDate, first: NaiveDate) -> Vec<NaiveDate> {
+        let month = first
+            .iter_days()
+            .take_while(|day| day.month() == first.month())
+            .collect::<Vec<_>>();
+        self.filter(&month, |day| {
+            !(self.by_day.is_empty() && self.by_month_day.is_empty()) || day.day() == start.day()
+        })
+    }
+
+    // Days of `scope` matching the BY* parts, and `default` for what those parts leave open.
+    // BYDAY ordinals count within `scope`, i.e. the month or the year.
+    fn filter(&self, scope: &[NaiveDate], default: impl Fn(&NaiveDate) -> bool) -> Vec<NaiveDate> {
+        let (Some(first), Some(last)) = (scope.first(), scope.last()) else {
+            return vec![];
+        };
+        scope
+            .iter()
+            .filter(|day| self.by_month.is_empty() || self.by_month.contains(&day.month()))
+            .filter(|day| {
+                self.by_month_day.is_empty()
+                    || self.by_month_day.iter().any(|month_day| {
+                        let last_day = i32::try_from(last_day_of_month(**day)).unwrap_or(31);
+                        let month_day = if *month_day < 0 {
+                            last_day + month_day + 1
+                        } else {
+                            *month_day
+                        };
+                        month_day == day.day() as i32
+                    })
+            })
+            .filter(|day| {
+                self.by_day.is_empty()
+                    || self.by_day.iter().any(|by_day| {
+                        let position = (**day - *first).num_days() / 7 + 1;
+                        let position_from_end = -((*last - **day).num_days() / 7 + 1);
+                        Weekday::from(by_day.day) == day.weekday()
+                            && match (by_day.ordinal, self.frequency) {
+                                (None, _) | (_, Frequency::Daily | Frequency::Weekly) => true,
+                                (Some(ordinal), _) => {
+                                    i64::from(ordinal) == position
+                                        || i64::from(ordinal) == position_from_end
+                                }
+                            }
+                    })
+            })
+            .filter(|day| default(day))
+            .copied()
+            .collect()
+    }
+}
+
+fn last_day_of_month(day: NaiveDate) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|last| day.with_day(*last).is_some())
+        .unwrap_or(28)
+}
+
+// BYSETPOS picks from a period's occurrences by position, negative positions count from the end.
+fn select_positions(candidates: &[NaiveDate], positions: &[i32]) -> Vec<NaiveDate> {
+    let mut selected = positions
+        .iter()
+        .filter_map(|position| {
+            let index = match *position {
+                position if position > 0 => position as usize - 1,
+                position => candidates
+                    .len()
+                    .checked_sub(position.unsigned_abs() as usize)?,
+            };
+            candidates.get(index).copied()
+        })
+        .collect::<Vec<_>>();
+    selected.sort();
+    selected.dedup();
+    selected
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(rule: &str) -> std::result::Result<Self, Self::Err> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut frequency = None;
+        let mut recurrence = Recurrence {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: vec![],
+            by_month_day: vec![],
+            by_month: vec![],
+            by_set_pos: vec![],
+            count: None,
+            until: None,
+            week_start: DayOfWeek::Monday,
+        };
+        let mut keys = HashSet::new();
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Malformed rule part {part}"))?;
+            let key = key.trim().to_ascii_uppercase();
+            if !keys.insert(key.clone()) {
+                return Err(format!("Duplicate rule part {key}"));
+            }
+            match key.as_str() {
+                "FREQ" => frequency = Some(parse_frequency(value)?),
+                "INTERVAL" => recurrence.interval = parse_number(&key, value, 1..=u32::MAX)?,
+                "COUNT" => recurrence.count = Some(parse_number(&key, value, 1..=u32::MAX)?),
+                "UNTIL" => recurrence.until = Some(parse_until(value)?),
+                "BYDAY" => recurrence.by_day = parse_list(value, parse_by_day)?,
+                "BYMONTHDAY" => {
+                    recurrence.by_month_day =
+                        parse_list(value, |value| parse_ordinal(&key, value, 31))?
+                }
+                "BYMONTH" => {
+                    recurrence.by_month =
+                        parse_list(value, |value| parse_number(&key, value, 1..=12))?
+                }
+                "BYSETPOS" => {
+                    recurrence.by_set_pos =
+                        parse_list(value, |value| parse_ordinal(&key, value, 366))?
+                }
+                "WKST" => recurrence.week_start = parse_day(value)?,
+                key if key.starts_with("X-") => {}
+                _ => return Err(format!("Unsupported rule part {key}")),
+            }
+        }
+        recurrence.frequency = frequency.ok_or("Missing FREQ")?;
+        if recurrence.count.is_some() && recurrence.until.is_some() {
+            return Err("COUNT and UNTIL cannot both be set".into());
+        }
+        Ok(recurrence)
+    }
+}
+
+fn parse_frequency(value: &str) -> std::result::Result<Frequency, String> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "DAILY" => Ok(Frequency::Daily),
+        "WEEKLY" => Ok(Frequency::Weekly),
+        "MONTHLY" => Ok(Frequency::Monthly),
+        "YEARLY" => Ok(Frequency::Yearly),
+        frequency => Err(format!("Unsupported frequency {frequency}")),
+    }
+}
+
+fn parse_number<T>(
+    key: &str,
+    value: &str,
+    valid: RangeInclusive<T>,
+) -> std::result::Result<T, String>
+where
+    T: FromStr + PartialOrd + Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .ok()
+        .filter(|number| valid.contains(number))
+        .ok_or_else(|| format!("Invalid {key} {value}"))
+}
+
+// non-zero and at most `max` either way, negative values count from the end
+fn parse_ordinal(key: &str, value: &str, max: i32) -> std::result::Result<i32, String> {
+    let ordinal = parse_number(key, value, -max..=max)?;
+    if ordinal == 0 {
+        return Err(format!("Invalid {key} {value}"));
+    }
+    Ok(ordinal)
+}
+
+fn parse_list<T>(
+    value: &str,
+    parse: impl Fn(&str) -> std::result::Result<T, String>,
+) -> std::result::Result<Vec<T>, String> {
+    value.split(',').map(parse).collect()
+}
+
+fn parse_day(value: &str) -> std::result::Result<DayOfWeek, String> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "MO" => Ok(DayOfWeek::Monday),
+        "TU" => Ok(DayOfWeek::Tuesday),
+        "WE" => Ok(DayOfWeek::Wednesday),
+        "TH" => Ok(DayOfWeek::Thursday),
+        "FR" => Ok(DayOfWeek::Friday),
+        "SA" => Ok(DayOfWeek::Saturday),
+        "SU" => Ok(DayOfWeek::Sunday),
+        day => Err(format!("Invalid day {day}")),
+    }
+}
+
+fn parse_by_day(value: &str) -> std::result::Result<ByDay, String> {
+    let value = value.trim();
+    let split = value
+        .len()
+        .checked_sub(2)
+        .filter(|split| value.is_char_boundary(*split))
+        .ok_or_else(|| format!("Invalid BYDAY {value}"))?;
+    let (ordinal, day) = value.split_at(split);
+    Ok(ByDay {
+        ordinal: match ordinal {
+            "" => None,
+            ordinal => Some(parse_ordinal("BYDAY", ordinal, 53)?),
+        },
+        day: parse_day(day)?,
+    })
+}
+
+// floating times are read as UTC
+fn parse_until(value: &str) -> std::result::Result<Until, String> {
+    let value = value.trim();
+    let utc = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+    NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+        .map(|date_time| Until::DateTime(date_time.and_utc()))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d").map(Until::Date))
+        .map_err(|_| format!("Invalid UNTIL {value}"))
+}
+
+impl FromSql for Recurrence {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?).map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
+impl ToSql for Recurrence {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let json =
+            serde_json::to_string(self).map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+#[cfg(test)]
+pub mod unit_tests {
+    use crate::api::DayOfWeek;
+    use crate::recurrence::{ByDay, Frequency, Recurrence, Until};
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    // Rules as returned by Calendar.app's `recurrence` property for the presets and custom
+    // repeats its event editor offers.
+    const CALENDAR_APP_RULES: &[&str] = &[
+        "FREQ=DAILY;INTERVAL=1",
+        "FREQ=DAILY;INTERVAL=3",
+        "FREQ=WEEKLY;INTERVAL=1",
+        "FREQ=WEEKLY;INTERVAL=2",
+        "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,TU,WE,TH,FR",
+        "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE;WKST=SU",
+        "FREQ=WEEKLY;INTERVAL=2;BYDAY=SA,SU;WKST=MO",
+        "FREQ=MONTHLY;INTERVAL=1",
+        "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=1,15",
+        "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=-1",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=2TU",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=-1FR",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=TU;BYSETPOS=2",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=SU,MO,TU,WE,TH,FR,SA;BYSETPOS=1",
+        "FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=10",
+        "FREQ=YEARLY;INTERVAL=1",
+        "FREQ=YEARLY;INTERVAL=1;BYMONTH=3,9",
+        "FREQ=YEARLY;INTERVAL=1;BYMONTH=5;BYDAY=2SU",
+        "FREQ=YEARLY;INTERVAL=1;BYMONTH=11;BYDAY=TH;BYSETPOS=4",
+        "FREQ=DAILY;INTERVAL=1;COUNT=10",
+        "FREQ=WEEKLY;INTERVAL=1;UNTIL=20251231T225959Z",
+        "FREQ=WEEKLY;INTERVAL=1;BYDAY=TU,TH;UNTIL=20260630T215959Z;WKST=SU",
+        "FREQ=MONTHLY;INTERVAL=1;BYDAY=1MO;COUNT=12",
+        "RRULE:FREQ=DAILY;INTERVAL=1;UNTIL=20251231",
+        "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO;X-APPLE-SOURCE=iCloud",
+    ];
+
+    #[test]
+    fn test_parse_calendar_app_rules() {
+        for rule in CALENDAR_APP_RULES {
+            if let Err(e) = rule.parse::<Recurrence>() {
+                panic!("Failed to parse {rule}: {e}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_recurrence() -> Result<(), String> {
+        assert_eq!(
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20251231T225959Z;WKST=SU"
+                .parse::<Recurrence>()?,
+            Recurrence {
+                frequency: Frequency::Weekly,
+                interval: 2,
+                by_day: vec![
+                    ByDay {
+                        ordinal: None,
+                        day: DayOfWeek::Monday,
+                    },
+                    ByDay {
+                        ordinal: None,
+                        day: DayOfWeek::Wednesday,
+                    },
+                ],
+                by_month_day: vec![],
+                by_month: vec![],
+                by_set_pos: vec![],
+                count: None,
+                until: Some(Until::DateTime(
+                    Utc.with_ymd_and_hms(2025, 12, 31, 22, 59, 59).unwrap()
+                )),
+                week_start: DayOfWeek::Sunday,
+            }
+        );
+        assert_eq!(
+            "freq=yearly;bymonth=5;byday=+2SU;count=3".parse::<Recurrence>()?,
+            Recurrence {
+                frequency: Frequency::Yearly,
+                interval: 1,
+                by_day: vec![ByDay {
+                    ordinal: Some(2),
+                    day: DayOfWeek::Sunday,
+                }],
+                by_month_day: vec![],
+                by_month: vec![5],
+                by_set_pos: vec![],
+                count: Some(3),
+                until: None,
+                week_start: DayOfWeek::Monday,
+            }
+        );
+        let monthly = "FREQ=MONTHLY;BYDAY=MO,FR;BYSETPOS=-1;BYMONTHDAY=-1,28;UNTIL=20251231"
+            .parse::<Recurrence>()?;
+        assert_eq!(monthly.by_set_pos, vec![-1]);
+        assert_eq!(monthly.by_month_day, vec![-1, 28]);
+        // dates end in the app's timezone, not in UTC
+        assert_eq!(
+            monthly.until,
+            Some(Until::Date(NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_occurrence_days() -> Result<(), String> {
+        let day = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let cases = [
+            (
+                "FREQ=DAILY;INTERVAL=3",
+                day(2025, 6, 1),
+                None,
+                day(2025, 6, 10),
+                vec![
+                    day(2025, 6, 1),
+                    day(2025, 6, 4),
+                    day(2025, 6, 7),
+                    day(2025, 6, 10),
+                ],
+            ),
+            (
+                "FREQ=DAILY;COUNT=3",
+                day(2025, 6, 1),
+                None,
+                day(2025, 6, 30),
+                vec![day(2025, 6, 1), day(2025, 6, 2), day(2025, 6, 3)],
+            ),
+            (
+                "FREQ=DAILY",
+                day(2025, 6, 1),
+                Some(day(2025, 6, 2)),
+                day(2025, 6, 30),
+                vec![day(2025, 6, 1), day(2025, 6, 2)],
+            ),
+            // without BYDAY, on the weekday of the first occurrence
+            (
+                "FREQ=WEEKLY",
+                day(2025, 6, 4),
+                None,
+                day(2025, 6, 18),
+                vec![day(2025, 6, 4), day(2025, 6, 11), day(2025, 6, 18)],
+            ),
+            (
+                "FREQ=WEEKLY;BYDAY=MO,WE",
+                day(2025, 6, 2),
+                None,
+                day(2025, 6, 15),
+                vec![
+                    day(2025, 6, 2),
+                    day(2025, 6, 4),
+                    day(2025, 6, 9),
+                    day(2025, 6, 11),
+                ],
+            ),
+            // the week start decides which days share a week
+            (
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=SU,TU;WKST=SU",
+                day(2025, 6, 3),
+                None,
+                day(2025, 6, 30),
+                vec![
+                    day(2025, 6, 3),
+                    day(2025, 6, 15),
+                    day(2025, 6, 17),
+                    day(2025, 6, 29),
+                ],
+            ),
+            (
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=SU,TU;WKST=MO",
+                day(2025, 6, 3),
+                None,
+                day(2025, 6, 30),
+                vec![
+                    day(2025, 6, 3),
+                    day(2025, 6, 8),
+                    day(2025, 6, 17),
+                    day(2025, 6, 22),
+                ],
+            ),
+            // months without the day are skipped
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=31",
+                day(2025, 1, 31),
+                None,
+                day(2025, 7, 31),
+                vec![
+                    day(2025, 1, 31),
+                    day(2025, 3, 31),
+                    day(2025, 5, 31),
+                    day(2025, 7, 31),
+                ],
+            ),
+            (
+                "FREQ=MONTHLY",
+                day(2025, 1, 31),
+                None,
+                day(2025, 4, 30),
+                vec![day(2025, 1, 31), day(2025, 3, 31)],
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                day(2025, 1, 31),
+                None,
+                day(2025, 4, 30),
+                vec![
+                    day(2025, 1, 31),
+                    day(2025, 2, 28),
+                    day(2025, 3, 31),
+                    day(2025, 4, 30),
+                ],
+            ),
+            (
+                "FREQ=MONTHLY;BYDAY=2TU",
+                day(2025, 6, 10),
+                None,
+                day(2025, 9, 30),
+                vec![
+                    day(2025, 6, 10),
+                    day(2025, 7, 8),
+                    day(2025, 8, 12),
+                    day(2025, 9, 9),
+                ],
+            ),
+            (
+                "FREQ=MONTHLY;BYDAY=-1FR",
+                day(2025, 6, 27),
+                None,
+                day(2025, 8, 31),
+                vec![day(2025, 6, 27), day(2025, 7, 25), day(2025, 8, 29)],
+            ),
+            // the last weekday of the month
+            (
+                "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+                day(2025, 5, 30),
+                None,
+                day(2025, 8, 31),
+                vec![
+                    day(2025, 5, 30),
+                    day(2025, 6, 30),
+                    day(2025, 7, 31),
+                    day(2025, 8, 29),
+                ],
+            ),
+            (
+                "FREQ=YEARLY",
+                day(2024, 2, 29),
+                None,
+                day(2029, 12, 31),
+                vec![day(2024, 2, 29), day(2028, 2, 29)],
+            ),
+            (
+                "FREQ=YEARLY;BYMONTH=5;BYDAY=2SU",
+                day(2025, 5, 11),
+                None,
+                day(2026, 12, 31),
+                vec![day(2025, 5, 11), day(2026, 5, 10)],
+            ),
+            (
+                "FREQ=YEARLY;BYMONTH=11;BYDAY=TH;BYSETPOS=4",
+                day(2025, 11, 27),
+                None,
+                day(2027, 12, 31),
+                vec![day(2025, 11, 27), day(2026, 11, 26), day(2027, 11, 25)],
+            ),
+            (
+                "FREQ=YEARLY;INTERVAL=2;BYMONTH=1,7;BYMONTHDAY=1",
+                day(2025, 1, 1),
+                None,
+                day(2027, 12, 31),
+                vec![
+                    day(2025, 1, 1),
+                    day(2025, 7, 1),
+                    day(2027, 1, 1),
+                    day(2027, 7, 1),
+                ],
+            ),
+        ];
+        for (rule, start, until, end, expected) in cases {
+            assert_eq!(
+                rule.parse::<Recurrence>()?
+                    .occurrence_days(start, until, end),
+                expected,
+                "{rule}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_recurrence() {
+        for (rule, error) in [
+            ("", "Missing FREQ"),
+            ("recurrence", "Malformed rule part recurrence"),
+            ("INTERVAL=2", "Missing FREQ"),
+            ("FREQ=HOURLY;INTERVAL=1", "Unsupported frequency HOURLY"),
+            ("FREQ=WEEKLY;INTERVAL=0", "Invalid INTERVAL 0"),
+            ("FREQ=WEEKLY;INTERVAL=-1", "Invalid INTERVAL -1"),
+            ("FREQ=WEEKLY;BYDAY=MO,XX", "Invalid day XX"),
+            ("FREQ=MONTHLY;BYDAY=0MO", "Invalid BYDAY 0"),
+            ("FREQ=MONTHLY;BYDAY=54MO", "Invalid BYDAY 54"),
+            ("FREQ=MONTHLY;BYMONTHDAY=32", "Invalid BYMONTHDAY 32"),
+            ("FREQ=MONTHLY;BYMONTHDAY=0", "Invalid BYMONTHDAY 0"),
+            ("FREQ=YEARLY;BYMONTH=13", "Invalid BYMONTH 13"),
+            ("FREQ=YEARLY;BYWEEKNO=20", "Unsupported rule part BYWEEKNO"),
+            ("FREQ=DAILY;BYHOUR=9", "Unsupported rule part BYHOUR"),
+            ("FREQ=DAILY;FREQ=WEEKLY", "Duplicate rule part FREQ"),
+            ("FREQ=DAILY;UNTIL=tomorrow", "Invalid UNTIL tomorrow"),
+            (
+                "FREQ=DAILY;COUNT=3;UNTIL=20250101T000000Z",
+                "COUNT and UNTIL cannot both be set",
+            ),
+        ] {
+            assert_eq!(rule.parse::<Recurrence>(), Err(error.to_string()), "{rule}");
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::api::{EventIds, HabitType};
+use crate::api::{EventIds, HabitKind, HabitType, Schedule};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Deserialize;
 use specta::Type;
 
@@ -8,6 +9,12 @@ pub struct CreateHabitRequest {
     pub event_ids: EventIds,
     pub title: String,
     pub question: String,
+    #[serde(default)]
+    pub kind: HabitKind,
+    pub unit: Option<String>,
+    pub target: Option<f64>,
+    #[serde(default)]
+    pub schedule: Schedule,
 }
 
 #[derive(Debug, Deserialize, Type)]
@@ -17,15 +24,37 @@ pub struct UpdateHabitRequest {
     pub event_ids: EventIds,
     pub title: String,
     pub question: String,
+    #[serde(default)]
+    pub kind: HabitKind,
+    pub unit: Option<String>,
+    pub target: Option<f64>,
+    #[serde(default)]
+    pub schedule: Schedule,
 }
 
 #[derive(Debug, Deserialize, Type)]
 pub struct InsertHabitEntryItem {
     pub habit_id: i64,
     pub completed: bool,
+    pub date: Option<DateTime<Utc>>,
+    pub value: Option<f64>,
 }
 
 #[derive(Debug, Deserialize, Type)]
 pub struct InsertHabitEntriesRequest {
     pub data: Vec<InsertHabitEntryItem>,
 }
+
+#[derive(Debug, Deserialize, Type)]
+pub struct UpdateHabitEntryRequest {
+    pub id: i64,
+    pub completed: bool,
+    pub date: DateTime<Utc>,
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Type)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
@@ -0,0 +1,53 @@
+use crate::{api::HabitTrackerService, error::HabitTrackerError, AppConfig};
+use r2d2::ManageConnection;
+
+// commands mostly read, and reads don't wait for each other
+const POOL_SIZE: u32 = 4;
+
+/// Opens services on the database of `app_config` for `r2d2`, each with its own connection.
+pub struct ServiceManager {
+    app_config: AppConfig,
+}
+
+impl ManageConnection for ServiceManager {
+    type Connection = HabitTrackerService;
+    type Error = HabitTrackerError;
+
+    fn connect(&self) -> Result<HabitTrackerService, HabitTrackerError> {
+        HabitTrackerService::build(self.app_config.clone())
+    }
+
+    fn is_valid(&self, service: &mut HabitTrackerService) -> Result<(), HabitTrackerError> {
+        Ok(service.conn.execute_batch("")?)
+    }
+
+    // a service left in a transaction, by a panic in the middle of it, isn't reused
+    fn has_broken(&self, service: &mut HabitTrackerService) -> bool {
+        !service.conn.is_autocommit()
+    }
+}
+
+pub type ServicePool = r2d2::Pool<ServiceManager>;
+
+/// Services on the same database, for callers that use it from several threads at once.
+/// Unlike a shared service behind a lock, one caller panicking doesn't affect the others.
+pub fn build_service_pool(app_config: AppConfig) -> Result<ServicePool, HabitTrackerError> {
+    // in-memory databases are private to their connection
+    let max_size = if app_config.db_path == ":memory:" {
+        1
+    } else {
+        POOL_SIZE
+    };
+    Ok(r2d2::Pool::builder()
+        .max_size(max_size)
+        .build(ServiceManager { app_config })?)
+}
+
+/// Runs `f` with a service of `pool`, waiting for one to be free if needed.
+pub fn with_service<T>(
+    pool: &ServicePool,
+    f: impl FnOnce(&mut HabitTrackerService) -> Result<T, HabitTrackerError>,
+) -> Result<T, HabitTrackerError> {
+    let mut service = pool.get()?;
+    f(&mut service)
+}
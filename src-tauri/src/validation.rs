@@ -0,0 +1,308 @@
+use crate::{
+    api::{EventIds, Habit, HabitKind, HabitType, Schedule},
+    app_config::{CalendarSourceKind, ConfigFile},
+    error::{FieldError, HabitTrackerError},
+    CreateHabitRequest, DateRange, InsertHabitEntriesRequest, UpdateHabitEntryRequest,
+    UpdateHabitRequest,
+};
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, OptionalExtension};
+
+// keep in line with habitFormSchema in src/forms/schemas.ts
+const TEXT_MIN_LENGTH: usize = 2;
+const TEXT_MAX_LENGTH: usize = 100;
+const UNIT_MAX_LENGTH: usize = 20;
+
+#[derive(Default)]
+struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    fn check_text(&mut self, field: &str, value: &str) {
+        match value.trim().chars().count() {
+            0 => self.push(field, "Required"),
+            length if length < TEXT_MIN_LENGTH => self.push(field, "Too short"),
+            length if length > TEXT_MAX_LENGTH => self.push(field, "Too long"),
+            _ => {}
+        }
+    }
+
+    fn into_result(self) -> Result<(), HabitTrackerError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(HabitTrackerError::validation(self.0))
+        }
+    }
+}
+
+// the fields shared by create and update requests
+struct HabitFields<'a> {
+    habit_type: &'a HabitType,
+    event_ids: &'a EventIds,
+    title: &'a str,
+    question: &'a str,
+    kind: &'a HabitKind,
+    unit: Option<&'a str>,
+    target: Option<f64>,
+    schedule: &'a Schedule,
+}
+
+impl<'a> From<&'a CreateHabitRequest> for HabitFields<'a> {
+    fn from(request: &'a CreateHabitRequest) -> Self {
+        HabitFields {
+            habit_type: &request.habit_type,
+            event_ids: &request.event_ids,
+            title: &request.title,
+            question: &request.question,
+            kind: &request.kind,
+            unit: request.unit.as_deref(),
+            target: request.target,
+            schedule: &request.schedule,
+        }
+    }
+}
+
+impl<'a> From<&'a UpdateHabitRequest> for HabitFields<'a> {
+    fn from(request: &'a UpdateHabitRequest) -> Self {
+        HabitFields {
+            habit_type: &request.habit_type,
+            event_ids: &request.event_ids,
+            title: &request.title,
+            question: &request.question,
+            kind: &request.kind,
+            unit: request.unit.as_deref(),
+            target: request.target,
+            schedule: &request.schedule,
+        }
+    }
+}
+
+pub fn validate_create_habit(
+    conn: &Connection,
+    request: &CreateHabitRequest,
+) -> Result<(), HabitTrackerError> {
+    check_habit_fields(conn, request.into(), &[])
+}
+
+/// Fails with `NotFound` rather than a field error when the habit itself does not exist.
+pub fn validate_update_habit(
+    conn: &Connection,
+    request: &UpdateHabitRequest,
+) -> Result<(), HabitTrackerError> {
+    let habit = conn
+        .query_row(
+            "SELECT * FROM habit WHERE id = ?1",
+            params![request.id],
+            Habit::from_row,
+        )
+        .optional()?
+        .ok_or_else(|| HabitTrackerError::NotFound {
+            message: format!("habit {} not found", request.id),
+        })?;
+    // events removed from the calendar since the habit was linked to them are kept
+    check_habit_fields(conn, request.into(), &habit.event_ids.values)
+}
+
+pub fn validate_insert_habit_entries(
+    conn: &Connection,
+    request: &InsertHabitEntriesRequest,
+) -> Result<(), HabitTrackerError> {
+    let mut errors = FieldErrors::default();
+    let mut statement = conn.prepare("SELECT EXISTS(SELECT 1 FROM habit WHERE id = ?1)")?;
+    for (index, item) in request.data.iter().enumerate() {
+        let habit_exists: bool = statement.query_row(params![item.habit_id], |row| row.get(0))?;
+        if !habit_exists {
+            errors.push(
+                format!("data[{index}].habit_id"),
+                format!("Unknown habit {}", item.habit_id),
+            );
+        }
+        check_value(&mut errors, &format!("data[{index}].value"), item.value);
+    }
+    errors.into_result()
+}
+
+pub fn validate_update_habit_entry(
+    request: &UpdateHabitEntryRequest,
+) -> Result<(), HabitTrackerError> {
+    let mut errors = FieldErrors::default();
+    check_value(&mut errors, "value", request.value);
+    errors.into_result()
+}
+
+pub fn validate_date_range(range: &DateRange) -> Result<(), HabitTrackerError> {
+    let mut errors = FieldErrors::default();
+    if range.start > range.end {
+        errors.push("range", "Start must not be after end");
+    }
+    errors.into_result()
+}
+
+pub fn validate_config_file(config: &ConfigFile) -> Result<(), HabitTrackerError> {
+    let mut errors = FieldErrors::default();
+    if config
+        .db_path
+        .as_ref()
+        .is_some_and(|path| path.trim().is_empty())
+    {
+        errors.push("db_path", "Required");
+    }
+    if let Some(timezone) = &config.timezone {
+        if timezone.parse::<Tz>().is_err() {
+            errors.push("timezone", format!("Unknown timezone {timezone}"));
+        }
+    }
+    if config.day_rollover_hour > 23 {
+        errors.push("day_rollover_hour", "Must be between 0 and 23");
+    }
+
+    let calendar = &config.calendar;
+    match calendar.source {
+        CalendarSourceKind::AppleScript if calendar.names.is_empty() => {
+            errors.push("calendar.names", "Required")
+        }
+        CalendarSourceKind::Ics if calendar.paths.is_empty() => {
+            errors.push("calendar.paths", "Required")
+        }
+        CalendarSourceKind::Fixtures if calendar.paths.len() != 1 => {
+            errors.push("calendar.paths", "Fixture sources read a single directory")
+        }
+        _ => {}
+    }
+    for (index, name) in calendar.names.iter().enumerate() {
+        if name.trim().is_empty() {
+            errors.push(format!("calendar.names[{index}]"), "Required");
+        }
+    }
+    for (index, path) in calendar.paths.iter().enumerate() {
+        if path.trim().is_empty() {
+            errors.push(format!("calendar.paths[{index}]"), "Required");
+        }
+    }
+
+    if config
+        .backups
+        .directory
+        .as_ref()
+        .is_some_and(|directory| directory.trim().is_empty())
+    {
+        errors.push("backups.directory", "Required");
+    }
+    if config.backups.keep_daily == 0 {
+        errors.push("backups.keep_daily", "Must be at least 1");
+    }
+
+    check_time(
+        &mut errors,
+        "reminders.sync_time",
+        &config.reminders.sync_time,
+    );
+    for (index, time) in config.reminders.notify_times.iter().enumerate() {
+        check_time(
+            &mut errors,
+            &format!("reminders.notify_times[{index}]"),
+            time,
+        );
+    }
+    errors.into_result()
+}
+
+fn check_habit_fields(
+    conn: &Connection,
+    habit: HabitFields,
+    known_event_ids: &[String],
+) -> Result<(), HabitTrackerError> {
+    let mut errors = FieldErrors::default();
+    errors.check_text("title", habit.title);
+    errors.check_text("question", habit.question);
+
+    match habit.habit_type {
+        HabitType::Daily if !habit.event_ids.values.is_empty() => errors.push(
+            "event_ids",
+            "Daily habits cannot be linked to calendar events",
+        ),
+        HabitType::AppleCalendar if habit.event_ids.values.is_empty() => {
+            errors.push("event_ids", "Required")
+        }
+        _ => {}
+    }
+    let mut statement = conn.prepare(
+        "SELECT recurrenceError, removedAt IS NOT NULL FROM appleCalendarEvent WHERE id = ?1",
+    )?;
+    for event_id in &habit.event_ids.values {
+        if known_event_ids.contains(event_id) {
+            continue;
+        }
+        let event: Option<(Option<String>, bool)> = statement
+            .query_row(params![event_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+        match event {
+            None => errors.push("event_ids", format!("Unknown calendar event {event_id}")),
+            Some((_, true)) => errors.push(
+                "event_ids",
+                format!("Calendar event {event_id} was removed from the calendar"),
+            ),
+            Some((Some(error), false)) => errors.push(
+                "event_ids",
+                format!("Calendar event {event_id} has an unsupported recurrence: {error}"),
+            ),
+            Some((None, false)) => {}
+        }
+    }
+
+    match habit.kind {
+        HabitKind::Measurable => match habit.target {
+            None => errors.push("target", "Required"),
+            Some(target) if !target.is_finite() || target <= 0.0 => {
+                errors.push("target", "Must be a positive number")
+            }
+            Some(_) => {}
+        },
+        HabitKind::YesNo if habit.target.is_some() => {
+            errors.push("target", "Only measurable habits have a target")
+        }
+        HabitKind::YesNo => {}
+    }
+    if habit
+        .unit
+        .is_some_and(|unit| unit.chars().count() > UNIT_MAX_LENGTH)
+    {
+        errors.push("unit", "Too long");
+    }
+
+    match habit.schedule {
+        _ if *habit.schedule != Schedule::EveryDay && *habit.habit_type != HabitType::Daily => {
+            errors.push("schedule", "Calendar habits follow their events")
+        }
+        Schedule::Weekdays { days } if days.is_empty() => errors.push("schedule", "Required"),
+        Schedule::EveryNDays { interval: 0, .. } => errors.push("schedule", "Must be at least 1"),
+        Schedule::TimesPerWeek { count } if !(1..=7).contains(count) => {
+            errors.push("schedule", "Must be between 1 and 7")
+        }
+        Schedule::TimesPerMonth { count } if !(1..=31).contains(count) => {
+            errors.push("schedule", "Must be between 1 and 31")
+        }
+        _ => {}
+    }
+    errors.into_result()
+}
+
+fn check_value(errors: &mut FieldErrors, field: &str, value: Option<f64>) {
+    if value.is_some_and(|value| !value.is_finite() || value < 0.0) {
+        errors.push(field, "Must be a positive number or zero");
+    }
+}
+
+fn check_time(errors: &mut FieldErrors, field: &str, time: &str) {
+    if NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+        errors.push(field, "Must be a time as HH:MM");
+    }
+}
@@ -0,0 +1,34 @@
+-- Database written before schema migrations were introduced (user_version 0),
+-- built with the `apple_calendar` feature on macOS.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL
+);
+
+INSERT INTO habit (habitType, eventIds, title, question) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?'),
+    ('appleCalendar', '["event-1"]', 'Gym', 'Did you go to the gym?');
+INSERT INTO habitEntry (completed, date, habitId) VALUES
+    (1, '2025-01-06 20:01:12', 1),
+    (0, '2025-01-06 20:01:12', 2),
+    (1, '2025-01-07 19:45:03', 1),
+    (1, '2025-01-08 21:10:44', 2);
+INSERT INTO appleCalendarEvent (id, name, startDate, recurrence) VALUES
+    ('event-1', 'Gym', '2024-12-30 18:00:00+00:00', 'FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE');
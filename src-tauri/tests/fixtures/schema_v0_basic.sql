@@ -0,0 +1,22 @@
+-- Database written before schema migrations were introduced (user_version 0),
+-- built without the `apple_calendar` feature, so it has no calendar event table.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+
+INSERT INTO habit (habitType, eventIds, title, question) VALUES
+    ('daily', '[]', 'Read', 'Did you read today?');
+INSERT INTO habitEntry (completed, date, habitId) VALUES
+    (1, '2025-02-01 22:30:00', 1),
+    (0, '2025-02-02 22:31:10', 1);
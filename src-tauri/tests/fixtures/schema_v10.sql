@@ -0,0 +1,50 @@
+-- Schema version 10: events moving an occurrence of a recurring event point to it.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL,
+    archivedAt REAL NULL,
+    kind TEXT CHECK(kind IN('yesNo', 'measurable')) NOT NULL DEFAULT 'yesNo',
+    unit TEXT NULL,
+    target REAL NULL,
+    schedule TEXT NULL,
+    removedEventIds TEXT NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    day TEXT NOT NULL DEFAULT '',
+    value REAL NULL,
+    timezone TEXT NULL,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE UNIQUE INDEX habitEntry_habitId_day ON habitEntry(habitId, day);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL,
+    excludedDates TEXT NULL,
+    recurrenceRule TEXT NULL,
+    recurrenceError TEXT NULL,
+    removedAt REAL NULL,
+    parentId TEXT NULL
+);
+PRAGMA user_version = 10;
+
+INSERT INTO appleCalendarEvent (id, name, startDate, recurrence, excludedDates, recurrenceRule, recurrenceError, removedAt, parentId) VALUES
+    ('event-1', 'Gym', '2025-06-02 07:00:00.000+00:00', 'FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE', '["2025-06-09T07:00:00Z", "2025-06-11T07:00:00Z"]', '{"frequency":"Weekly","interval":1,"by_day":[{"ordinal":null,"day":"Monday"},{"ordinal":null,"day":"Wednesday"}],"by_month_day":[],"by_month":[],"by_set_pos":[],"count":null,"until":null,"week_start":"Monday"}', NULL, NULL, NULL),
+    ('event-1/20250611T070000Z', 'Gym', '2025-06-12 07:00:00.000+00:00', 'FREQ=DAILY;COUNT=1', NULL, '{"frequency":"Daily","interval":1,"by_day":[],"by_month_day":[],"by_month":[],"by_set_pos":[],"count":1,"until":null,"week_start":"Monday"}', NULL, NULL, 'event-1'),
+    ('event-2', 'Standup', '2025-06-02 09:00:00.000+00:00', 'FREQ=HOURLY;INTERVAL=1', NULL, NULL, 'Unsupported frequency HOURLY', '2025-06-05 08:00:00.000+00:00', NULL),
+    ('event-3', 'Plants', '2025-06-01 18:00:00.000+00:00', 'FREQ=DAILY;UNTIL=20251231', NULL, '{"frequency":"Daily","interval":1,"by_day":[],"by_month_day":[],"by_month":[],"by_set_pos":[],"count":null,"until":"2025-12-31T23:59:59Z","week_start":"Monday"}', NULL, NULL, NULL);
+INSERT INTO habit (habitType, eventIds, title, question, schedule, removedEventIds) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?', NULL, NULL),
+    ('appleCalendar', '["event-1", "event-2"]', 'Gym', 'Did you go to the gym?', NULL, '["event-2"]');
+INSERT INTO habitEntry (completed, date, habitId, day, timezone) VALUES
+    (1, '2025-06-02 12:00:00.000+00:00', 1, '2025-06-02', 'Europe/Berlin'),
+    (1, '2025-06-02 07:30:00.000+00:00', 2, '2025-06-02', 'Europe/Berlin');
@@ -0,0 +1,34 @@
+-- Schema version 2: habits can be archived. Entries are not yet unique per day,
+-- so this fixture contains a duplicate check-in for 2025-03-05.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL,
+    archivedAt REAL NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL
+);
+PRAGMA user_version = 2;
+
+INSERT INTO habit (habitType, eventIds, title, question, archivedAt) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?', NULL),
+    ('daily', '[]', 'Journal', 'Did you journal today?', '2025-03-01 09:00:00.000+00:00');
+INSERT INTO habitEntry (completed, date, habitId) VALUES
+    (1, '2025-03-04 12:00:00.000+00:00', 1),
+    (0, '2025-03-05 12:00:00.000+00:00', 1),
+    (1, '2025-03-05 12:30:00.000+00:00', 1),
+    (1, '2025-02-27 12:00:00.000+00:00', 2);
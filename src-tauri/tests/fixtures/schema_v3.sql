@@ -0,0 +1,36 @@
+-- Schema version 3: entries carry a local day key and are unique per habit and day.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL,
+    archivedAt REAL NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    day TEXT NOT NULL DEFAULT '',
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE UNIQUE INDEX habitEntry_habitId_day ON habitEntry(habitId, day);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL
+);
+PRAGMA user_version = 3;
+
+INSERT INTO habit (habitType, eventIds, title, question, archivedAt) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?', NULL),
+    ('appleCalendar', '["event-1"]', 'Gym', 'Did you go to the gym?', NULL);
+INSERT INTO habitEntry (completed, date, habitId, day) VALUES
+    (1, '2025-04-01 12:00:00.000+00:00', 1, '2025-04-01'),
+    (0, '2025-04-02 12:00:00.000+00:00', 1, '2025-04-02'),
+    (1, '2025-04-02 12:00:00.000+00:00', 2, '2025-04-02');
+INSERT INTO appleCalendarEvent (id, name, startDate, recurrence) VALUES
+    ('event-1', 'Gym', '2025-03-31 18:00:00+00:00', 'FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE');
@@ -0,0 +1,38 @@
+-- Schema version 4: measurable habits with a unit and target, entries with a value.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL,
+    archivedAt REAL NULL,
+    kind TEXT CHECK(kind IN('yesNo', 'measurable')) NOT NULL DEFAULT 'yesNo',
+    unit TEXT NULL,
+    target REAL NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    day TEXT NOT NULL DEFAULT '',
+    value REAL NULL,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE UNIQUE INDEX habitEntry_habitId_day ON habitEntry(habitId, day);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL
+);
+PRAGMA user_version = 4;
+
+INSERT INTO habit (habitType, eventIds, title, question, kind, unit, target) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?', 'yesNo', NULL, NULL),
+    ('daily', '[]', 'Drink water', 'How many glasses did you drink?', 'measurable', 'glasses', 8);
+INSERT INTO habitEntry (completed, date, habitId, day, value) VALUES
+    (1, '2025-05-01 12:00:00.000+00:00', 1, '2025-05-01', NULL),
+    (0, '2025-05-01 12:00:00.000+00:00', 2, '2025-05-01', 6),
+    (1, '2025-05-02 12:00:00.000+00:00', 2, '2025-05-02', 9.5);
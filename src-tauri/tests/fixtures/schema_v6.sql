@@ -0,0 +1,42 @@
+-- Schema version 6: entries record the timezone they were checked in from.
+CREATE TABLE habit (
+    id INTEGER PRIMARY KEY,
+    habitType TEXT CHECK(habitType IN('daily', 'appleCalendar')) NOT NULL DEFAULT 'daily',
+    eventIds TEXT NULL,
+    title TEXT NOT NULL,
+    question TEXT NOT NULL,
+    archivedAt REAL NULL,
+    kind TEXT CHECK(kind IN('yesNo', 'measurable')) NOT NULL DEFAULT 'yesNo',
+    unit TEXT NULL,
+    target REAL NULL,
+    schedule TEXT NULL
+);
+CREATE TABLE habitEntry (
+    id INTEGER PRIMARY KEY,
+    completed BOOLEAN NOT NULL CHECK(completed IN (0, 1)),
+    date REAL NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    habitId INTEGER,
+    day TEXT NOT NULL DEFAULT '',
+    value REAL NULL,
+    timezone TEXT NULL,
+    FOREIGN KEY(habitId) REFERENCES habit(id)
+);
+CREATE UNIQUE INDEX habitEntry_habitId_day ON habitEntry(habitId, day);
+CREATE TABLE appleCalendarEvent (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    startDate REAL NOT NULL,
+    endDate REAL NULL,
+    recurrence TEXT NOT NULL
+);
+PRAGMA user_version = 6;
+
+INSERT INTO appleCalendarEvent (id, name, startDate, recurrence) VALUES
+    ('event-1', 'Gym', '2025-06-02 07:00:00.000+00:00', 'FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE');
+INSERT INTO habit (habitType, eventIds, title, question, schedule) VALUES
+    ('daily', '[]', 'Meditate', 'Did you meditate today?', NULL),
+    ('appleCalendar', '["event-1"]', 'Gym', 'Did you go to the gym?', NULL);
+INSERT INTO habitEntry (completed, date, habitId, day, timezone) VALUES
+    (1, '2025-06-02 12:00:00.000+00:00', 1, '2025-06-02', 'Europe/Berlin'),
+    (1, '2025-06-02 07:30:00.000+00:00', 2, '2025-06-02', 'Europe/Berlin'),
+    (0, '2025-06-04 21:30:00.000+00:00', 1, '2025-06-04', NULL);
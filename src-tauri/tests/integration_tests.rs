@@ -1,9 +1,21 @@
-use chrono::Utc;
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
 use habit_tracker_lib::{
-    api::{AppleCalendarEvent, EventIds, HabitType},
-    app_config::get_test_app_config,
-    CreateHabitRequest, Habit, HabitTrackerService, InsertHabitEntriesRequest,
-    InsertHabitEntryItem, UpdateHabitRequest,
+    api::{
+        AppleCalendarEvent, CalendarSyncReport, DayOfWeek, EventIds, ExcludedDates, HabitKind,
+        HabitType, Schedule, StatsGranularity,
+    },
+    app_config::{get_test_app_config, read_config_file, write_config_file, ConfigFile},
+    backup::{create_backup, list_backups, restore_backup, BackupConfig, BackupReason},
+    calendar_source::{build_calendar_source, CalendarSourceConfig},
+    csv_export::{export_csv, CsvLayout},
+    doctor::DoctorIssue,
+    export::{export_archive, import_archive, HabitTrackerArchive, ImportCounts, ImportMode},
+    ics::import_ics_files,
+    recurrence::Frequency,
+    service_pool::{build_service_pool, with_service},
+    AppConfig, CreateHabitRequest, DateRange, Habit, HabitTrackerError, HabitTrackerService,
+    InsertHabitEntriesRequest, InsertHabitEntryItem, UpdateHabitEntryRequest, UpdateHabitRequest,
 };
 use rusqlite::Result;
 use std::error::Error;
@@ -13,22 +25,53 @@ fn mock_habit_tracker_service() -> Result<HabitTrackerService, Box<dyn Error>> {
     Ok(HabitTrackerService::build(app_config)?)
 }
 
+fn mock_apple_calendar_events(
+    habit_tracker_service: &mut HabitTrackerService,
+    ids: &[&str],
+) -> Result<(), Box<dyn Error>> {
+    habit_tracker_service.sync_calendar_events(
+        ids.iter()
+            .map(|id| AppleCalendarEvent {
+                id: id.to_string(),
+                name: id.to_string(),
+                start_date: Utc::now(),
+                recurrence: "FREQ=DAILY;INTERVAL=1".to_string(),
+                excluded_dates: ExcludedDates::default(),
+                parent_id: None,
+                recurrence_rule: None,
+                recurrence_error: None,
+                removed_at: None,
+            })
+            .collect(),
+    )?;
+    Ok(())
+}
+
 #[test]
 fn test_habit_methods() -> Result<(), Box<dyn Error>> {
-    let habit_tracker_service = mock_habit_tracker_service()?;
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut habit_tracker_service, &["eventId", "updatedEventId"])?;
     habit_tracker_service.create_habit(CreateHabitRequest {
         habit_type: HabitType::Daily,
-        event_ids: EventIds {
-            values: vec!["eventId".to_owned()],
-        },
+        event_ids: EventIds { values: vec![] },
         title: "some title".to_string(),
         question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
     })?;
     habit_tracker_service.create_habit(CreateHabitRequest {
         habit_type: HabitType::AppleCalendar,
-        event_ids: EventIds { values: vec![] },
+        event_ids: EventIds {
+            values: vec!["eventId".into()],
+        },
         title: "some other title".to_string(),
         question: "some other question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
     })?;
     habit_tracker_service.update_habit(UpdateHabitRequest {
         id: 1,
@@ -38,8 +81,12 @@ fn test_habit_methods() -> Result<(), Box<dyn Error>> {
         },
         title: "updated title".to_string(),
         question: "updated question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
     })?;
-    let habits = habit_tracker_service.get_habits()?;
+    let habits = habit_tracker_service.get_habits(false)?;
     assert_eq!(habits.len(), 2);
     assert_eq!(
         habits,
@@ -52,13 +99,27 @@ fn test_habit_methods() -> Result<(), Box<dyn Error>> {
                 },
                 title: "updated title".to_string(),
                 question: "updated question".to_string(),
+                archived_at: None,
+                kind: HabitKind::YesNo,
+                unit: None,
+                target: None,
+                schedule: Schedule::EveryDay,
+                removed_event_ids: EventIds::default(),
             },
             Habit {
                 id: 2,
                 habit_type: HabitType::AppleCalendar,
-                event_ids: EventIds { values: vec![] },
+                event_ids: EventIds {
+                    values: vec!["eventId".into()]
+                },
                 title: "some other title".to_string(),
                 question: "some other question".to_string(),
+                archived_at: None,
+                kind: HabitKind::YesNo,
+                unit: None,
+                target: None,
+                schedule: Schedule::EveryDay,
+                removed_event_ids: EventIds::default(),
             }
         ],
     );
@@ -66,31 +127,218 @@ fn test_habit_methods() -> Result<(), Box<dyn Error>> {
 }
 
 #[test]
-fn test_habit_entry_methods() -> Result<(), Box<dyn Error>> {
+fn test_archive_and_delete_habit_methods() -> Result<(), Box<dyn Error>> {
     let mut habit_tracker_service = mock_habit_tracker_service()?;
-    habit_tracker_service.create_habit(CreateHabitRequest {
-        habit_type: HabitType::Daily,
+    for title in ["first habit", "second habit"] {
+        habit_tracker_service.create_habit(CreateHabitRequest {
+            habit_type: HabitType::Daily,
+            event_ids: EventIds { values: vec![] },
+            title: title.to_string(),
+            question: "some question".to_string(),
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+        })?;
+    }
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![
+            InsertHabitEntryItem {
+                habit_id: 1,
+                completed: true,
+                date: None,
+                value: None,
+            },
+            InsertHabitEntryItem {
+                habit_id: 2,
+                completed: true,
+                date: None,
+                value: None,
+            },
+        ],
+    })?;
+
+    // archiving hides the habit but keeps its entries
+    let archived_habit = habit_tracker_service.archive_habit(1)?;
+    assert!(archived_habit.archived_at.is_some());
+    let habits = habit_tracker_service.get_habits(false)?;
+    assert_eq!(habits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(habit_tracker_service.get_habits(true)?.len(), 2);
+    assert_eq!(habit_tracker_service.get_habit_entries()?.len(), 2);
+
+    let unarchived_habit = habit_tracker_service.unarchive_habit(1)?;
+    assert_eq!(unarchived_habit.archived_at, None);
+    assert_eq!(habit_tracker_service.get_habits(false)?.len(), 2);
+
+    // deleting removes the habit along with its entries
+    habit_tracker_service.delete_habit(1)?;
+    let habits = habit_tracker_service.get_habits(true)?;
+    assert_eq!(habits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![2]);
+    let habit_entries = habit_tracker_service.get_habit_entries()?;
+    assert_eq!(
+        habit_entries.iter().map(|e| e.habit_id).collect::<Vec<_>>(),
+        vec![2]
+    );
+    assert!(matches!(
+        habit_tracker_service.delete_habit(1),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    assert!(matches!(
+        habit_tracker_service.archive_habit(1),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_habit_validation_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut habit_tracker_service, &["eventId"])?;
+    let field_errors = |error: HabitTrackerError| match error {
+        HabitTrackerError::Validation { fields, .. } => fields
+            .into_iter()
+            .map(|field_error| field_error.field)
+            .collect::<Vec<_>>(),
+        error => panic!("expected a validation error, got {error:?}"),
+    };
+
+    let error = habit_tracker_service
+        .create_habit(CreateHabitRequest {
+            habit_type: HabitType::Daily,
+            event_ids: EventIds {
+                values: vec!["eventId".into()],
+            },
+            title: " ".to_string(),
+            question: "some question".to_string(),
+            kind: HabitKind::Measurable,
+            unit: None,
+            target: None,
+            schedule: Schedule::TimesPerWeek { count: 8 },
+        })
+        .unwrap_err();
+    assert_eq!(
+        field_errors(error),
+        vec!["title", "event_ids", "target", "schedule"]
+    );
+    let error = habit_tracker_service
+        .create_habit(CreateHabitRequest {
+            habit_type: HabitType::AppleCalendar,
+            event_ids: EventIds {
+                values: vec!["unknownEventId".into()],
+            },
+            title: "some title".to_string(),
+            question: "some question".to_string(),
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+        })
+        .unwrap_err();
+    assert_eq!(field_errors(error), vec!["event_ids"]);
+
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
         event_ids: EventIds {
             values: vec!["eventId".into()],
         },
         title: "some title".to_string(),
         question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
     })?;
-    habit_tracker_service.create_habit(CreateHabitRequest {
+    // events that disappeared from the calendar can still be kept on update
+    mock_apple_calendar_events(&mut habit_tracker_service, &[])?;
+    let update_request = |id: i64| UpdateHabitRequest {
+        id,
         habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["eventId".into()],
+        },
+        title: "updated title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    };
+    assert_eq!(
+        habit_tracker_service
+            .update_habit(update_request(habit.id))?
+            .title,
+        "updated title"
+    );
+    assert!(matches!(
+        habit_tracker_service.update_habit(update_request(2)),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+
+    let error = habit_tracker_service
+        .insert_habit_entries(InsertHabitEntriesRequest {
+            data: vec![
+                InsertHabitEntryItem {
+                    habit_id: habit.id,
+                    completed: true,
+                    date: None,
+                    value: Some(-1.0),
+                },
+                InsertHabitEntryItem {
+                    habit_id: 2,
+                    completed: true,
+                    date: None,
+                    value: None,
+                },
+            ],
+        })
+        .unwrap_err();
+    assert_eq!(
+        field_errors(error),
+        vec!["data[0].value", "data[1].habit_id"]
+    );
+    assert!(habit_tracker_service.get_habit_entries()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_habit_entry_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut habit_tracker_service, &["eventId"])?;
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
         event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["eventId".into()],
+        },
         title: "some other title".to_string(),
         question: "some other question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
     })?;
     let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
         data: vec![
             InsertHabitEntryItem {
                 habit_id: 2,
                 completed: true,
+                date: None,
+                value: None,
             },
             InsertHabitEntryItem {
                 habit_id: 1,
                 completed: false,
+                date: None,
+                value: None,
             },
         ],
     })?;
@@ -113,65 +361,1889 @@ fn test_habit_entry_methods() -> Result<(), Box<dyn Error>> {
         (2, 1, false)
     );
     assert!((habit_entries[1].date - Utc::now()).num_seconds().abs() < 5);
+
+    // checking in again on the same day replaces the previous entry
+    let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: 1,
+            completed: true,
+            date: None,
+            value: None,
+        }],
+    })?;
+    assert_eq!(
+        (
+            habit_entries[0].id,
+            habit_entries[0].habit_id,
+            habit_entries[0].completed
+        ),
+        (2, 1, true)
+    );
+    assert_eq!(habit_tracker_service.get_habit_entries()?.len(), 2);
     Ok(())
 }
 
 #[test]
-fn test_apple_calendar_event_methods() -> Result<(), Box<dyn Error>> {
+fn test_backdated_and_edited_habit_entry_methods() -> Result<(), Box<dyn Error>> {
     let mut habit_tracker_service = mock_habit_tracker_service()?;
-    let events = habit_tracker_service.reset_apple_calendar_events(vec![
-        AppleCalendarEvent {
-            id: "appleCalendarEventId1".to_string(),
-            name: "appleCalendarEventName1".to_string(),
-            start_date: Utc::now(),
-            recurrence: "recurrence".to_string(),
-        },
-        AppleCalendarEvent {
-            id: "appleCalendarEventId2".to_string(),
-            name: "appleCalendarEventName2".to_string(),
-            start_date: Utc::now(),
-            recurrence: "recurrence".to_string(),
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let yesterday = Utc::now() - Duration::days(1);
+    let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: 1,
+            completed: false,
+            date: Some(yesterday),
+            value: None,
+        }],
+    })?;
+    assert_eq!(habit_entries.len(), 1);
+    assert_eq!(habit_entries[0].date, yesterday);
+
+    let two_days_ago = yesterday - Duration::days(1);
+    let habit_entry = habit_tracker_service.update_habit_entry(UpdateHabitEntryRequest {
+        id: habit_entries[0].id,
+        completed: true,
+        date: two_days_ago,
+        value: None,
+    })?;
+    assert!(habit_entry.completed);
+    assert_eq!(habit_entry.date, two_days_ago);
+    assert_eq!(
+        habit_tracker_service.get_habit_entries()?,
+        vec![habit_entry]
+    );
+
+    habit_tracker_service.delete_habit_entry(habit_entries[0].id)?;
+    assert!(habit_tracker_service.get_habit_entries()?.is_empty());
+    assert!(matches!(
+        habit_tracker_service.delete_habit_entry(habit_entries[0].id),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    assert!(matches!(
+        habit_tracker_service.update_habit_entry(UpdateHabitEntryRequest {
+            id: habit_entries[0].id,
+            completed: true,
+            date: yesterday,
+            value: None,
+        }),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_day_rollover_habit_entry_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = HabitTrackerService::build(AppConfig {
+        timezone: Tz::America__New_York,
+        day_rollover_hour: 3,
+        ..get_test_app_config()?
+    })?;
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    // 02:30 EDT after midnight still counts towards the evening before
+    let late_check_in = Utc.with_ymd_and_hms(2025, 6, 2, 6, 30, 0).unwrap();
+    let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: 1,
+            completed: true,
+            date: Some(late_check_in),
+            value: None,
+        }],
+    })?;
+    assert_eq!(
+        habit_entries[0].day,
+        NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+    );
+    assert_eq!(
+        habit_entries[0].timezone.as_deref(),
+        Some("America/New_York")
+    );
+
+    let habit_entry = habit_tracker_service.update_habit_entry(UpdateHabitEntryRequest {
+        id: habit_entries[0].id,
+        completed: true,
+        date: late_check_in + Duration::hours(1),
+        value: None,
+    })?;
+    assert_eq!(
+        habit_entry.day,
+        NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()
+    );
+
+    // check-ins for another day count towards that day, whatever the rollover hour
+    for day in [
+        NaiveDate::from_ymd_opt(2025, 3, 9).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 11, 2).unwrap(),
+    ] {
+        assert_eq!(
+            habit_tracker_service.local_day(habit_tracker_service.date_on_day(day)),
+            day
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_measurable_habit_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "drink water".to_string(),
+        question: "How many glasses did you drink?".to_string(),
+        kind: HabitKind::Measurable,
+        unit: Some("glasses".to_string()),
+        target: Some(8.0),
+        schedule: Schedule::EveryDay,
+    })?;
+    assert_eq!(
+        (habit.kind, habit.unit.as_deref(), habit.target),
+        (HabitKind::Measurable, Some("glasses"), Some(8.0))
+    );
+
+    // completion is derived from the value, whatever the completed flag says
+    let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: habit.id,
+            completed: true,
+            date: None,
+            value: Some(5.0),
+        }],
+    })?;
+    assert_eq!(
+        (habit_entries[0].value, habit_entries[0].completed),
+        (Some(5.0), false)
+    );
+    let habit_entries = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            habit_id: habit.id,
+            completed: false,
+            date: None,
+            value: Some(8.0),
+        }],
+    })?;
+    assert_eq!(
+        (habit_entries[0].value, habit_entries[0].completed),
+        (Some(8.0), true)
+    );
+
+    // raising the target re-derives completion of existing entries
+    habit_tracker_service.update_habit(UpdateHabitRequest {
+        id: habit.id,
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "drink water".to_string(),
+        question: "How many glasses did you drink?".to_string(),
+        kind: HabitKind::Measurable,
+        unit: Some("glasses".to_string()),
+        target: Some(10.0),
+        schedule: Schedule::EveryDay,
+    })?;
+    let habit_entries = habit_tracker_service.get_habit_entries()?;
+    assert_eq!(
+        (habit_entries[0].value, habit_entries[0].completed),
+        (Some(8.0), false)
+    );
+
+    let habit_entry = habit_tracker_service.update_habit_entry(UpdateHabitEntryRequest {
+        id: habit_entries[0].id,
+        completed: false,
+        date: habit_entries[0].date,
+        value: Some(12.5),
+    })?;
+    assert_eq!(
+        (habit_entry.value, habit_entry.completed),
+        (Some(12.5), true)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_habit_streak_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let days_ago = |days: i64| Some(Utc::now() - Duration::days(days));
+    habit_tracker_service.sync_calendar_events(vec![AppleCalendarEvent {
+        id: "eventId".to_string(),
+        name: "every other day".to_string(),
+        start_date: days_ago(10).unwrap(),
+        recurrence: "FREQ=DAILY;INTERVAL=2".to_string(),
+        excluded_dates: ExcludedDates::default(),
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    }])?;
+    for (habit_type, event_ids) in [
+        (HabitType::Daily, vec![]),
+        (HabitType::AppleCalendar, vec!["eventId".to_string()]),
+    ] {
+        habit_tracker_service.create_habit(CreateHabitRequest {
+            habit_type,
+            event_ids: EventIds { values: event_ids },
+            title: "some title".to_string(),
+            question: "some question".to_string(),
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+        })?;
+    }
+    let entries = [
+        (1, 6, true),
+        (1, 5, true),
+        (1, 4, true),
+        // nothing tracked 3 days ago breaks the daily habit's streak
+        (1, 2, true),
+        (1, 1, true),
+        (2, 6, true),
+        (2, 4, false),
+        // the event doesn't occur 3 days ago, so this entry doesn't count
+        (2, 3, true),
+        (2, 2, true),
+        (2, 0, true),
+    ];
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: entries
+            .map(|(habit_id, days, completed)| InsertHabitEntryItem {
+                habit_id,
+                completed,
+                date: days_ago(days),
+                value: None,
+            })
+            .into(),
+    })?;
+
+    let streaks = habit_tracker_service.get_habit_streaks()?;
+    assert_eq!(
+        streaks
+            .iter()
+            .map(|s| (s.habit_id, s.current_streak, s.longest_streak))
+            .collect::<Vec<_>>(),
+        vec![(1, 2, 3), (2, 2, 2)]
+    );
+    assert_eq!(
+        streaks[0].last_break_date,
+        days_ago(3).map(|date| habit_tracker_service.local_day(date))
+    );
+    assert_eq!(
+        streaks[1].last_break_date,
+        days_ago(4).map(|date| habit_tracker_service.local_day(date))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_habit_stats_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let entries = [(3, 30, true), (3, 31, true), (4, 1, false), (4, 3, true)];
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: entries
+            .map(|(month, day, completed)| InsertHabitEntryItem {
+                habit_id: 1,
+                completed,
+                date: Some(Utc.with_ymd_and_hms(2025, month, day, 12, 0, 0).unwrap()),
+                value: None,
+            })
+            .into(),
+    })?;
+    let date = |month: u32, day: u32| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    let range = DateRange {
+        start: date(3, 29),
+        end: date(4, 5),
+    };
+    let summarize = |granularity| -> Result<Vec<_>, Box<dyn Error>> {
+        let stats = habit_tracker_service.get_habit_stats(1, range, granularity)?;
+        Ok(stats
+            .buckets
+            .into_iter()
+            .map(|b| {
+                (
+                    b.period,
+                    b.start,
+                    b.end,
+                    b.completed_count,
+                    b.due_count,
+                    b.completion_rate,
+                )
+            })
+            .collect())
+    };
+
+    // the habit is due daily from its first entry on 2025-03-30
+    assert_eq!(
+        summarize(StatsGranularity::Week)?,
+        vec![
+            ("2025-W13".to_string(), date(3, 29), date(3, 30), 1, 1, 1.0),
+            (
+                "2025-W14".to_string(),
+                date(3, 31),
+                date(4, 5),
+                2,
+                6,
+                2.0 / 6.0
+            ),
+        ]
+    );
+    assert_eq!(
+        summarize(StatsGranularity::Month)?,
+        vec![
+            ("2025-03".to_string(), date(3, 29), date(3, 31), 2, 2, 1.0),
+            ("2025-04".to_string(), date(4, 1), date(4, 5), 1, 5, 0.2),
+        ]
+    );
+    assert_eq!(
+        summarize(StatsGranularity::Year)?,
+        vec![("2025".to_string(), date(3, 29), date(4, 5), 3, 7, 3.0 / 7.0)]
+    );
+    assert!(matches!(
+        habit_tracker_service.get_habit_stats(2, range, StatsGranularity::Week),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    assert!(matches!(
+        habit_tracker_service.get_habit_stats(
+            1,
+            DateRange {
+                start: range.end,
+                end: range.start,
+            },
+            StatsGranularity::Week,
+        ),
+        Err(HabitTrackerError::Validation { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_habit_schedule_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let today = habit_tracker_service.today();
+    let every_other_day = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryNDays {
+            interval: 2,
+            start: today - Duration::days(6),
         },
-        AppleCalendarEvent {
-            id: "appleCalendarEventId3".to_string(),
-            name: "appleCalendarEventName3".to_string(),
-            start_date: Utc::now(),
-            recurrence: "recurrence".to_string(),
+    })?;
+    let not_today = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some other title".to_string(),
+        question: "some other question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::Weekdays {
+            days: vec![(today + Duration::days(1)).weekday().into()],
         },
-    ])?;
-    assert_eq!(events.len(), 3);
+    })?;
     assert_eq!(
-        (
-            events[1].id.clone(),
-            events[1].name.clone(),
-            events[1].recurrence.clone()
-        ),
-        (
-            "appleCalendarEventId2".to_string(),
-            "appleCalendarEventName2".to_string(),
-            "recurrence".to_string()
-        )
+        every_other_day.schedule,
+        Schedule::EveryNDays {
+            interval: 2,
+            start: today - Duration::days(6)
+        }
     );
-    assert!((events[1].start_date - Utc::now()).num_seconds().abs() < 5);
-    let events = habit_tracker_service.reset_apple_calendar_events(vec![AppleCalendarEvent {
-        id: "updatedAppleCalendarEventId1".to_string(),
-        name: "updatedAppleCalendarEventName1".to_string(),
-        start_date: Utc::now(),
-        recurrence: "updatedRecurrence".to_string(),
-    }])?;
-    assert_eq!(events.len(), 1);
+    assert_ne!(not_today.schedule, Schedule::EveryDay);
+
+    let due_habits = habit_tracker_service.get_due_habits(today)?;
+    assert_eq!(
+        due_habits.iter().map(|h| h.id).collect::<Vec<_>>(),
+        vec![every_other_day.id]
+    );
+    let due_habits = habit_tracker_service.get_due_habits(today + Duration::days(1))?;
+    assert_eq!(
+        due_habits.iter().map(|h| h.id).collect::<Vec<_>>(),
+        vec![not_today.id]
+    );
+
+    // skipping days the habit is not due on keeps the streak going
+    let days_ago = |days: i64| Some(Utc::now() - Duration::days(days));
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: [6, 4, 2]
+            .map(|days| InsertHabitEntryItem {
+                habit_id: every_other_day.id,
+                completed: true,
+                date: days_ago(days),
+                value: None,
+            })
+            .into(),
+    })?;
+    let streaks = habit_tracker_service.get_habit_streaks()?;
     assert_eq!(
         (
-            events[0].id.clone(),
-            events[0].name.clone(),
-            events[0].recurrence.clone()
+            streaks[0].current_streak,
+            streaks[0].longest_streak,
+            streaks[0].last_break_date
         ),
-        (
-            "updatedAppleCalendarEventId1".to_string(),
-            "updatedAppleCalendarEventName1".to_string(),
-            "updatedRecurrence".to_string(),
-        )
+        (3, 3, None)
     );
-    assert!((events[0].start_date - Utc::now()).num_seconds().abs() < 5);
+    Ok(())
+}
+
+#[test]
+fn test_due_date_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    let at = |month, day, hour| Utc.with_ymd_and_hms(2025, month, day, hour, 0, 0).unwrap();
+    let monday = || AppleCalendarEvent {
+        id: "mondayEventId".to_string(),
+        name: "Monday".to_string(),
+        start_date: at(6, 2, 7),
+        recurrence: "FREQ=WEEKLY;BYDAY=MO".to_string(),
+        excluded_dates: ExcludedDates {
+            values: vec![at(6, 9, 7)],
+        },
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    };
+    let wednesday = || AppleCalendarEvent {
+        id: "wednesdayEventId".to_string(),
+        name: "Wednesday".to_string(),
+        start_date: at(6, 4, 18),
+        recurrence: "FREQ=WEEKLY;BYDAY=WE;COUNT=2".to_string(),
+        excluded_dates: ExcludedDates::default(),
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    };
+    habit_tracker_service.sync_calendar_events(vec![monday(), wednesday()])?;
+    let calendar_habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec![monday().id, wednesday().id],
+        },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let daily_habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some other title".to_string(),
+        question: "some other question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::Weekdays {
+            days: vec![Weekday::Mon.into()],
+        },
+    })?;
+    let range = DateRange {
+        start: day(6, 1),
+        end: day(6, 22),
+    };
+
+    // occurrences of both events are merged, without the excluded Monday
+    assert_eq!(
+        habit_tracker_service.get_due_dates(calendar_habit.id, range)?,
+        vec![day(6, 2), day(6, 4), day(6, 11), day(6, 16)]
+    );
+    assert_eq!(
+        habit_tracker_service.get_due_dates(daily_habit.id, range)?,
+        vec![day(6, 2), day(6, 9), day(6, 16)]
+    );
+    let due_habits = habit_tracker_service.get_due_habits(day(6, 4))?;
+    assert_eq!(
+        due_habits.iter().map(|h| h.id).collect::<Vec<_>>(),
+        vec![calendar_habit.id]
+    );
+    let due_habits = habit_tracker_service.get_due_habits(day(6, 9))?;
+    assert_eq!(
+        due_habits.iter().map(|h| h.id).collect::<Vec<_>>(),
+        vec![daily_habit.id]
+    );
+    assert!(habit_tracker_service.get_due_habits(day(6, 10))?.is_empty());
+
+    // events removed from the calendar no longer occur
+    habit_tracker_service.sync_calendar_events(vec![wednesday()])?;
+    assert_eq!(
+        habit_tracker_service.get_due_dates(calendar_habit.id, range)?,
+        vec![day(6, 4), day(6, 11)]
+    );
+
+    assert!(matches!(
+        habit_tracker_service.get_due_dates(-1, range),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+    assert!(matches!(
+        habit_tracker_service.get_due_dates(
+            daily_habit.id,
+            DateRange {
+                start: day(6, 22),
+                end: day(6, 1),
+            }
+        ),
+        Err(HabitTrackerError::Validation { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_due_dates_until_date() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = HabitTrackerService::build(AppConfig {
+        timezone: Tz::Asia__Tokyo,
+        ..get_test_app_config()?
+    })?;
+    let day = |day| NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+    // 23:00 in Tokyo, the day before in UTC
+    habit_tracker_service.sync_calendar_events(vec![AppleCalendarEvent {
+        id: "lateEventId".to_string(),
+        name: "Late".to_string(),
+        start_date: Utc.with_ymd_and_hms(2025, 6, 1, 14, 0, 0).unwrap(),
+        recurrence: "FREQ=DAILY;UNTIL=20250603".to_string(),
+        excluded_dates: ExcludedDates::default(),
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    }])?;
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["lateEventId".to_string()],
+        },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+
+    // a date-only UNTIL ends with the local day, not the UTC one
+    assert_eq!(
+        habit_tracker_service.get_due_dates(
+            habit.id,
+            DateRange {
+                start: day(1),
+                end: day(10),
+            }
+        )?,
+        vec![day(1), day(2), day(3)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_due_dates_with_day_rollover() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = HabitTrackerService::build(AppConfig {
+        timezone: Tz::Europe__Paris,
+        day_rollover_hour: 3,
+        ..get_test_app_config()?
+    })?;
+    let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    // all-day events start at local midnight, before the rollover hour
+    let midnight = |month, day| {
+        Tz::Europe__Paris
+            .with_ymd_and_hms(2025, month, day, 0, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+    habit_tracker_service.sync_calendar_events(vec![AppleCalendarEvent {
+        id: "allDayEventId".to_string(),
+        name: "All day".to_string(),
+        start_date: midnight(6, 2),
+        recurrence: "FREQ=WEEKLY;UNTIL=20250616".to_string(),
+        excluded_dates: ExcludedDates {
+            values: vec![midnight(6, 9)],
+        },
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    }])?;
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["allDayEventId".to_string()],
+        },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+
+    // the rollover hour moves late check-ins, not the days events fall on
+    assert_eq!(
+        habit_tracker_service.get_due_dates(
+            habit.id,
+            DateRange {
+                start: day(6, 1),
+                end: day(6, 30),
+            }
+        )?,
+        vec![day(6, 2), day(6, 16)]
+    );
+    Ok(())
+}
+#[test]
+fn test_apple_calendar_event_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let events = habit_tracker_service
+        .sync_calendar_events(vec![
+            AppleCalendarEvent {
+                id: "appleCalendarEventId1".to_string(),
+                name: "appleCalendarEventName1".to_string(),
+                start_date: Utc::now(),
+                recurrence: "recurrence".to_string(),
+                excluded_dates: ExcludedDates::default(),
+                parent_id: None,
+                recurrence_rule: None,
+                recurrence_error: None,
+                removed_at: None,
+            },
+            AppleCalendarEvent {
+                id: "appleCalendarEventId2".to_string(),
+                name: "appleCalendarEventName2".to_string(),
+                start_date: Utc::now(),
+                recurrence: "recurrence".to_string(),
+                excluded_dates: ExcludedDates::default(),
+                parent_id: None,
+                recurrence_rule: None,
+                recurrence_error: None,
+                removed_at: None,
+            },
+            AppleCalendarEvent {
+                id: "appleCalendarEventId3".to_string(),
+                name: "appleCalendarEventName3".to_string(),
+                start_date: Utc::now(),
+                recurrence: "recurrence".to_string(),
+                excluded_dates: ExcludedDates::default(),
+                parent_id: None,
+                recurrence_rule: None,
+                recurrence_error: None,
+                removed_at: None,
+            },
+        ])?
+        .added;
+    assert_eq!(events.len(), 3);
+    assert_eq!(
+        (
+            events[1].id.clone(),
+            events[1].name.clone(),
+            events[1].recurrence.clone()
+        ),
+        (
+            "appleCalendarEventId2".to_string(),
+            "appleCalendarEventName2".to_string(),
+            "recurrence".to_string()
+        )
+    );
+    assert!((events[1].start_date - Utc::now()).num_seconds().abs() < 5);
+    // unparseable rules are stored flagged
+    assert_eq!(events[1].recurrence_rule, None);
+    assert_eq!(
+        events[1].recurrence_error.as_deref(),
+        Some("Malformed rule part recurrence")
+    );
+    match habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["appleCalendarEventId2".to_string()],
+        },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    }) {
+        Err(HabitTrackerError::Validation { fields, .. }) => assert_eq!(
+            fields[0].message,
+            "Calendar event appleCalendarEventId2 has an unsupported recurrence: Malformed rule part recurrence"
+        ),
+        other => panic!("Expected Validation error, got {other:?}"),
+    }
+    let events = habit_tracker_service
+        .sync_calendar_events(vec![AppleCalendarEvent {
+            id: "updatedAppleCalendarEventId1".to_string(),
+            name: "updatedAppleCalendarEventName1".to_string(),
+            start_date: Utc::now(),
+            recurrence: "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO".to_string(),
+            excluded_dates: ExcludedDates {
+                values: vec![Utc.with_ymd_and_hms(2025, 6, 9, 5, 0, 0).unwrap()],
+            },
+            parent_id: None,
+            recurrence_rule: None,
+            recurrence_error: None,
+            removed_at: None,
+        }])?
+        .added;
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        (
+            events[0].id.clone(),
+            events[0].name.clone(),
+            events[0].recurrence.clone()
+        ),
+        (
+            "updatedAppleCalendarEventId1".to_string(),
+            "updatedAppleCalendarEventName1".to_string(),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO".to_string(),
+        )
+    );
+    let rule = events[0].recurrence_rule.as_ref().unwrap();
+    assert_eq!((rule.frequency, rule.interval), (Frequency::Weekly, 2));
+    assert_eq!(events[0].recurrence_error, None);
+    assert!((events[0].start_date - Utc::now()).num_seconds().abs() < 5);
+    assert_eq!(
+        events[0].excluded_dates.values,
+        vec![Utc.with_ymd_and_hms(2025, 6, 9, 5, 0, 0).unwrap()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_calendar_sync_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let event = |id: &str, name: &str| AppleCalendarEvent {
+        id: id.to_string(),
+        name: name.to_string(),
+        start_date: Utc.with_ymd_and_hms(2025, 6, 2, 7, 0, 0).unwrap(),
+        recurrence: "FREQ=DAILY".to_string(),
+        excluded_dates: ExcludedDates::default(),
+        parent_id: None,
+        recurrence_rule: None,
+        recurrence_error: None,
+        removed_at: None,
+    };
+    let ids = |events: &[AppleCalendarEvent]| {
+        events
+            .iter()
+            .map(|event| event.id.clone())
+            .collect::<Vec<_>>()
+    };
+    let report = habit_tracker_service.sync_calendar_events(vec![
+        event("a", "A"),
+        event("b", "B"),
+        event("c", "C"),
+    ])?;
+    assert_eq!(ids(&report.added), vec!["a", "b", "c"]);
+    let create_habit = |event_id: &str| CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec![event_id.to_string()],
+        },
+        title: format!("linked to {event_id}"),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    };
+    let linked_to_b = habit_tracker_service.create_habit(create_habit("b"))?;
+    let linked_to_c = habit_tracker_service.create_habit(create_habit("c"))?;
+
+    let report = habit_tracker_service.sync_calendar_events(vec![
+        event("a", "A"),
+        event("b", "B renamed"),
+        event("d", "D"),
+    ])?;
+    assert_eq!(ids(&report.added), vec!["d"]);
+    assert_eq!(ids(&report.updated), vec!["b"]);
+    assert_eq!(report.updated[0].name, "B renamed");
+    assert_eq!(ids(&report.removed), vec!["c"]);
+    assert!(report.removed[0].removed_at.is_some());
+    assert_eq!(
+        report
+            .flagged_habits
+            .iter()
+            .map(|habit| (habit.id, habit.removed_event_ids.values.clone()))
+            .collect::<Vec<_>>(),
+        vec![(linked_to_c.id, vec!["c".to_string()])]
+    );
+    // removed events are kept as tombstones, out of sight
+    assert_eq!(
+        ids(&habit_tracker_service.get_apple_calendar_events()?),
+        vec!["a", "b", "d"]
+    );
+    match habit_tracker_service.create_habit(create_habit("c")) {
+        Err(HabitTrackerError::Validation { fields, .. }) => assert_eq!(
+            fields[0].message,
+            "Calendar event c was removed from the calendar"
+        ),
+        other => panic!("Expected Validation error, got {other:?}"),
+    }
+    let habits = habit_tracker_service.get_habits(false)?;
+    assert_eq!(habits[0].removed_event_ids, EventIds::default());
+    assert_eq!(habits[1].removed_event_ids.values, vec!["c".to_string()]);
+
+    // syncing again changes nothing
+    let unchanged = habit_tracker_service.sync_calendar_events(vec![
+        event("a", "A"),
+        event("b", "B renamed"),
+        event("d", "D"),
+    ])?;
+    assert_eq!(unchanged, CalendarSyncReport::default());
+
+    // events coming back are added again and no longer flag their habits
+    let report = habit_tracker_service.sync_calendar_events(vec![
+        event("a", "A"),
+        event("b", "B renamed"),
+        event("c", "C"),
+        event("d", "D"),
+    ])?;
+    assert_eq!(ids(&report.added), vec!["c"]);
+    assert!(report.flagged_habits.is_empty());
+    let habits = habit_tracker_service.get_habits(false)?;
+    assert_eq!(habits[1].removed_event_ids, EventIds::default());
+
+    // relinking a flagged habit clears the flag
+    let report = habit_tracker_service.sync_calendar_events(vec![event("a", "A")])?;
+    assert_eq!(ids(&report.removed), vec!["b", "c", "d"]);
+    assert_eq!(report.flagged_habits.len(), 2);
+    let relinked = habit_tracker_service.update_habit(UpdateHabitRequest {
+        id: linked_to_b.id,
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["b".to_string(), "a".to_string()],
+        },
+        title: linked_to_b.title,
+        question: linked_to_b.question,
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    assert_eq!(relinked.removed_event_ids, EventIds::default());
+
+    assert!(matches!(
+        habit_tracker_service.sync_calendar_events(vec![event("a", "A"), event("a", "A")]),
+        Err(HabitTrackerError::Conflict { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_calendar_source_methods() -> Result<(), Box<dyn Error>> {
+    let app_config = get_test_app_config()?;
+    let source = build_calendar_source(&app_config.calendar_source, app_config.timezone);
+    assert!(app_config.calendar_source.is_available());
+    let mut habit_tracker_service = HabitTrackerService::build(app_config)?;
+    let ids = |events: &[AppleCalendarEvent]| {
+        events
+            .iter()
+            .map(|event| event.id.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // fixture files are read in name order
+    let report = habit_tracker_service.sync_calendar_events(source.events()?)?;
+    assert_eq!(ids(&report.added), vec!["gym", "standup", "review"]);
+    assert_eq!(
+        report.added[0].start_date,
+        Utc.with_ymd_and_hms(2025, 6, 2, 7, 0, 0).unwrap()
+    );
+    assert!(report.added[1].recurrence_error.is_some());
+    assert_eq!(
+        report.added[2].excluded_dates.values,
+        vec![Utc.with_ymd_and_hms(2025, 6, 13, 16, 0, 0).unwrap()]
+    );
+
+    let directory = std::env::temp_dir().join(format!(
+        "habit-tracker-calendar-source-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory)?;
+    std::fs::write(
+        directory.join("home.json"),
+        r#"[{ "id": "gym", "name": "Gym at home", "start_date": 1748847600, "recurrence": "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO,WE" },
+            { "id": "standup", "name": "Standup", "start_date": 1748854800, "recurrence": "FREQ=HOURLY;INTERVAL=1" }]"#,
+    )?;
+    std::fs::write(directory.join("notes.txt"), "not a calendar")?;
+    let source = build_calendar_source(
+        &CalendarSourceConfig::Fixtures {
+            directory: directory.clone(),
+        },
+        Tz::UTC,
+    );
+    let report = habit_tracker_service.sync_calendar_events(source.events()?)?;
+    assert_eq!(
+        (
+            ids(&report.added),
+            ids(&report.updated),
+            ids(&report.removed)
+        ),
+        (vec![], vec!["gym".to_string()], vec!["review".to_string()])
+    );
+
+    std::fs::write(directory.join("work.json"), "{ not json")?;
+    assert!(matches!(
+        source.events(),
+        Err(HabitTrackerError::CalendarUnavailable { .. })
+    ));
+    std::fs::remove_dir_all(&directory)?;
+    assert!(matches!(
+        source.events(),
+        Err(HabitTrackerError::CalendarUnavailable { .. })
+    ));
+    #[cfg(not(all(target_os = "macos", feature = "apple_calendar")))]
+    assert!(matches!(
+        build_calendar_source(
+            &CalendarSourceConfig::AppleScript {
+                calendar_names: vec!["Home".to_string()]
+            },
+            Tz::UTC
+        )
+        .events(),
+        Err(HabitTrackerError::CalendarUnavailable { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_import_ics_files() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut habit_tracker_service, &["staleEventId"])?;
+    let directory = std::env::temp_dir().join(format!("habit-tracker-ics-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let event = |uid: &str, summary: &str| {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{summary}\r\nDTSTART:20250602T070000Z\r\nRRULE:FREQ=DAILY\r\nEXDATE:20250603T070000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+    };
+    let home = directory.join("home.ics");
+    let work = directory.join("work.ics");
+    std::fs::write(
+        &home,
+        event("shared", "Stretch") + &event("home", "Water plants"),
+    )?;
+    std::fs::write(&work, event("shared", "Stretch at work"))?;
+
+    let report = import_ics_files(&mut habit_tracker_service, &[&home, &work])?;
+    let events = habit_tracker_service.get_apple_calendar_events()?;
+    let mut imported = events
+        .iter()
+        .map(|event| (event.id.as_str(), event.name.as_str()))
+        .collect::<Vec<_>>();
+    imported.sort();
+    // stored events are replaced, and the first file wins for events in several files
+    assert_eq!(
+        imported,
+        vec![("home", "Water plants"), ("shared", "Stretch")]
+    );
+    assert_eq!((report.added.len(), report.removed.len()), (2, 1));
+    assert_eq!(
+        events[0].excluded_dates.values,
+        vec![Utc.with_ymd_and_hms(2025, 6, 3, 7, 0, 0).unwrap()]
+    );
+
+    let missing = directory.join("missing.ics");
+    assert!(matches!(
+        import_ics_files(&mut habit_tracker_service, &[&missing]),
+        Err(HabitTrackerError::CalendarUnavailable { .. })
+    ));
+    std::fs::write(&work, "BEGIN:VEVENT\r\nRRULE:FREQ=DAILY\r\nEND:VEVENT\r\n")?;
+    match import_ics_files(&mut habit_tracker_service, &[&work]) {
+        Err(HabitTrackerError::Validation { fields, .. }) => {
+            assert_eq!(fields[0].field, format!("{}: line 1", work.display()));
+        }
+        other => panic!("Expected Validation error, got {other:?}"),
+    }
+    // failed imports leave the stored events alone
+    assert_eq!(habit_tracker_service.get_apple_calendar_events()?.len(), 2);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_import_ics_moved_occurrences() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-ics-moved-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let path = directory.join("gym.ics");
+    // 2025-06-02 is a Monday, its occurrence moves to Tuesday
+    std::fs::write(
+        &path,
+        "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:gym\r\nSUMMARY:Gym\r\nDTSTART:20250602T070000Z\r\nRRULE:FREQ=WEEKLY;BYDAY=MO\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nUID:gym\r\nRECURRENCE-ID:20250602T070000Z\r\nDTSTART:20250603T070000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )?;
+    import_ics_files(&mut habit_tracker_service, &[&path])?;
+    // moved occurrences come with their recurring event
+    assert_eq!(
+        habit_tracker_service
+            .get_apple_calendar_events()?
+            .iter()
+            .map(|event| event.id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["gym"]
+    );
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["gym".to_string()],
+        },
+        title: "Gym".to_string(),
+        question: "Did you go to the gym?".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let day = |day| NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+    assert_eq!(
+        habit_tracker_service.get_due_dates(
+            habit.id,
+            DateRange {
+                start: day(1),
+                end: day(10),
+            }
+        )?,
+        vec![day(3), day(9)]
+    );
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_import_methods() -> Result<(), Box<dyn Error>> {
+    let mut source = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut source, &["eventId"])?;
+    for (habit_type, event_ids, title) in [
+        (HabitType::Daily, vec![], "some title"),
+        (
+            HabitType::AppleCalendar,
+            vec!["eventId".to_string()],
+            "some other title",
+        ),
+    ] {
+        source.create_habit(CreateHabitRequest {
+            habit_type,
+            event_ids: EventIds { values: event_ids },
+            title: title.to_string(),
+            question: "some question".to_string(),
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+        })?;
+    }
+    let days_ago = |days: i64| Some(Utc::now() - Duration::days(days));
+    source.insert_habit_entries(InsertHabitEntriesRequest {
+        data: [(1, 2), (1, 1), (2, 1)]
+            .map(|(habit_id, days)| InsertHabitEntryItem {
+                habit_id,
+                completed: true,
+                date: days_ago(days),
+                value: None,
+            })
+            .into(),
+    })?;
+    let archive = export_archive(&source)?;
+    assert_eq!(
+        (
+            archive.habits.len(),
+            archive.habit_entries.len(),
+            archive.calendar_events.len()
+        ),
+        (2, 3, 1)
+    );
+    let archive: HabitTrackerArchive = serde_json::from_str(&serde_json::to_string(&archive)?)?;
+    let counts = |added, skipped, conflicted| ImportCounts {
+        added,
+        skipped,
+        conflicted,
+    };
+
+    // habits are remapped past the ones already in the database
+    let mut target = mock_habit_tracker_service()?;
+    target.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "existing title".to_string(),
+        question: "existing question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let report = import_archive(&mut target, &archive, ImportMode::Merge)?;
+    assert_eq!(report.habits, counts(2, 0, 0));
+    assert_eq!(report.habit_entries, counts(3, 0, 0));
+    assert_eq!(report.calendar_events, counts(1, 0, 0));
+    assert_eq!(
+        target
+            .get_habit_entries()?
+            .iter()
+            .map(|entry| entry.habit_id)
+            .collect::<Vec<_>>(),
+        vec![2, 2, 3]
+    );
+
+    // importing again only finds what is already there
+    let report = import_archive(&mut target, &archive, ImportMode::Merge)?;
+    assert_eq!(report.habits, counts(0, 2, 0));
+    assert_eq!(report.habit_entries, counts(0, 3, 0));
+    assert_eq!(report.calendar_events, counts(0, 1, 0));
+
+    // local changes are kept and reported as conflicts
+    target.update_habit(UpdateHabitRequest {
+        id: 2,
+        habit_type: HabitType::Daily,
+        event_ids: EventIds { values: vec![] },
+        title: "some title".to_string(),
+        question: "updated question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let report = import_archive(&mut target, &archive, ImportMode::Merge)?;
+    assert_eq!(report.habits, counts(0, 1, 1));
+    assert_eq!(target.get_habits(true)?[1].question, "updated question");
+
+    let report = import_archive(&mut target, &archive, ImportMode::Replace)?;
+    assert_eq!(report.habits, counts(2, 0, 0));
+    assert_eq!(report.habit_entries, counts(3, 0, 0));
+    let exported = export_archive(&target)?;
+    assert_eq!(
+        exported
+            .habits
+            .iter()
+            .map(|habit| (habit.title.as_str(), habit.question.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("some title", "some question"),
+            ("some other title", "some question")
+        ]
+    );
+    assert_eq!(exported.calendar_events, archive.calendar_events);
+    Ok(())
+}
+
+#[test]
+fn test_export_import_removed_events() -> Result<(), Box<dyn Error>> {
+    let mut source = mock_habit_tracker_service()?;
+    mock_apple_calendar_events(&mut source, &["eventId", "removedEventId"])?;
+    source.create_habit(CreateHabitRequest {
+        habit_type: HabitType::AppleCalendar,
+        event_ids: EventIds {
+            values: vec!["eventId".to_string(), "removedEventId".to_string()],
+        },
+        title: "some title".to_string(),
+        question: "some question".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    mock_apple_calendar_events(&mut source, &["eventId"])?;
+
+    // the tombstone of the removed event is archived, as the habit still links to it
+    let archive = export_archive(&source)?;
+    let removed_event = archive
+        .calendar_events
+        .iter()
+        .find(|event| event.id == "removedEventId")
+        .unwrap();
+    assert!(removed_event.removed_at.is_some());
+    let archive: HabitTrackerArchive = serde_json::from_str(&serde_json::to_string(&archive)?)?;
+
+    let mut target = mock_habit_tracker_service()?;
+    let report = import_archive(&mut target, &archive, ImportMode::Replace)?;
+    assert_eq!(report.calendar_events.added, 2);
+    assert_eq!(
+        export_archive(&target)?.calendar_events,
+        archive.calendar_events
+    );
+    assert_eq!(
+        target
+            .get_apple_calendar_events()?
+            .iter()
+            .map(|event| event.id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["eventId"]
+    );
+    let habit = &target.get_habits(true)?[0];
+    assert_eq!(habit.event_ids, source.get_habits(true)?[0].event_ids);
+    assert_eq!(habit.removed_event_ids.values, vec!["removedEventId"]);
+    assert!(target.doctor(false)?.issues.is_empty());
+
+    // importing again finds the tombstone already there
+    let report = import_archive(&mut target, &archive, ImportMode::Merge)?;
+    assert_eq!(report.calendar_events.skipped, 2);
+    Ok(())
+}
+
+#[test]
+fn test_csv_export_methods() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = mock_habit_tracker_service()?;
+    for (title, kind, unit, target) in [
+        ("Meditate", HabitKind::YesNo, None, None),
+        ("Run, outside", HabitKind::Measurable, Some("km"), Some(5.0)),
+    ] {
+        habit_tracker_service.create_habit(CreateHabitRequest {
+            habit_type: HabitType::Daily,
+            event_ids: EventIds { values: vec![] },
+            title: title.to_string(),
+            question: "some question".to_string(),
+            kind,
+            unit: unit.map(str::to_string),
+            target,
+            schedule: Schedule::EveryDay,
+        })?;
+    }
+    let entries = [
+        (1, 1, true, None),
+        (2, 1, true, Some(6.5)),
+        (1, 2, false, None),
+        (2, 4, false, Some(3.0)),
+    ];
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: entries
+            .map(|(habit_id, day, completed, value)| InsertHabitEntryItem {
+                habit_id,
+                completed,
+                date: Some(Utc.with_ymd_and_hms(2025, 6, day, 20, 0, 0).unwrap()),
+                value,
+            })
+            .into(),
+    })?;
+    let export = |layout, range| -> Result<String, Box<dyn Error>> {
+        let mut csv = vec![];
+        export_csv(&habit_tracker_service, &mut csv, layout, range)?;
+        Ok(String::from_utf8(csv)?)
+    };
+
+    assert_eq!(
+        export(CsvLayout::Long, None)?,
+        "habit_id,habit_title,day,completed,value,unit,recorded_at
+1,Meditate,2025-06-01,1,,,2025-06-01T20:00:00+00:00
+2,\"Run, outside\",2025-06-01,1,6.5,km,2025-06-01T20:00:00+00:00
+1,Meditate,2025-06-02,0,,,2025-06-02T20:00:00+00:00
+2,\"Run, outside\",2025-06-04,0,3,km,2025-06-04T20:00:00+00:00
+"
+    );
+    assert_eq!(
+        export(CsvLayout::Wide, None)?,
+        "day,Meditate,\"Run, outside\"
+2025-06-01,1,6.5
+2025-06-02,0,
+2025-06-03,,
+2025-06-04,,3
+"
+    );
+    let date = |day: u32| NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+    assert_eq!(
+        export(
+            CsvLayout::Wide,
+            Some(DateRange {
+                start: date(2),
+                end: date(3),
+            })
+        )?,
+        "day,Meditate
+2025-06-02,0
+2025-06-03,
+"
+    );
+    assert!(matches!(
+        export_csv(
+            &habit_tracker_service,
+            vec![],
+            CsvLayout::Long,
+            Some(DateRange {
+                start: date(3),
+                end: date(2),
+            })
+        ),
+        Err(HabitTrackerError::Validation { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_config_file_methods() -> Result<(), Box<dyn Error>> {
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-config-{}", std::process::id()));
+    let path = directory.join("config.toml");
+    assert_eq!(read_config_file(&path)?, ConfigFile::default());
+
+    let mut config = ConfigFile {
+        week_start: DayOfWeek::Sunday,
+        ..Default::default()
+    };
+    config.calendar.names = vec!["Home".to_string(), "Sport".to_string()];
+    config.reminders.notify_times = vec!["08:30".to_string(), "20:00".to_string()];
+    write_config_file(&path, &config)?;
+    assert_eq!(read_config_file(&path)?, config);
+
+    // the background services script gets the times of a written file through habit-sync
+    let output = std::process::Command::new("bash")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../scripts/setup-background-services.sh"
+        ))
+        .arg("--print-times")
+        .env("HABIT_TRACKER_CONFIG_PATH", &path)
+        .env("HABIT_SYNC", env!("CARGO_BIN_EXE_habit-sync"))
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "14:00\n08:30 20:00\n");
+
+    // invalid configs are not written, and are rejected by field when read
+    config.day_rollover_hour = 24;
+    config.reminders.sync_time = "2pm".to_string();
+    let Err(HabitTrackerError::Validation { fields, .. }) = write_config_file(&path, &config)
+    else {
+        panic!("expected a validation error");
+    };
+    assert_eq!(
+        fields
+            .iter()
+            .map(|field| field.field.as_str())
+            .collect::<Vec<_>>(),
+        vec!["day_rollover_hour", "reminders.sync_time"]
+    );
+    std::fs::write(&path, "day_rollover_hour = 24\n")?;
+    assert!(matches!(
+        read_config_file(&path),
+        Err(HabitTrackerError::Validation { .. })
+    ));
+    std::fs::write(&path, "day_rollover_hour = \"3\"\n")?;
+    assert!(matches!(
+        read_config_file(&path),
+        Err(HabitTrackerError::Validation { .. })
+    ));
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_habit_command_done() -> Result<(), Box<dyn Error>> {
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-command-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let db_path = directory.join("habits.db");
+    let habit = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_habit"))
+            .args(args)
+            .arg("--json")
+            .env("HABIT_TRACKER_DB_PATH", &db_path)
+            .env("HABIT_TRACKER_CONFIG_PATH", directory.join("config.toml"))
+            .output()
+    };
+    let validation_error = |output: std::process::Output| -> Result<bool, Box<dyn Error>> {
+        let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+        Ok(!output.status.success() && error["code"] == "Validation")
+    };
+    assert!(
+        habit(&["add", "Read", "--unit", "pages", "--target", "20"])?
+            .status
+            .success()
+    );
+    assert!(habit(&["add", "Run"])?.status.success());
+
+    // measurable habits need a value, which a check-in without one must not replace
+    assert!(habit(&["done", "Read", "--value", "25"])?.status.success());
+    assert!(validation_error(habit(&["done", "Read"])?)?);
+    // yes/no habits have none
+    assert!(validation_error(habit(&["done", "Run", "--value", "1"])?)?);
+    assert!(habit(&["done", "Run"])?.status.success());
+
+    let habit_tracker_service = HabitTrackerService::build(AppConfig {
+        db_path: db_path.to_string_lossy().into_owned(),
+        ..get_test_app_config()?
+    })?;
+    assert_eq!(
+        habit_tracker_service
+            .get_habit_entries()?
+            .iter()
+            .map(|entry| (entry.habit_id, entry.completed, entry.value))
+            .collect::<Vec<_>>(),
+        vec![(1, true, Some(25.0)), (2, true, None)]
+    );
+    drop(habit_tracker_service);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_week_start() -> Result<(), Box<dyn Error>> {
+    let mut habit_tracker_service = HabitTrackerService::build(AppConfig {
+        week_start: DayOfWeek::Sunday,
+        ..get_test_app_config()?
+    })?;
+    let habit = habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds::default(),
+        title: "Run".to_string(),
+        question: "Did you run?".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    // Saturday 2025-06-07 and Sunday 2025-06-08
+    let date = |day: u32| Utc.with_ymd_and_hms(2025, 6, day, 12, 0, 0).unwrap();
+    habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: [7, 8]
+            .into_iter()
+            .map(|day| InsertHabitEntryItem {
+                habit_id: habit.id,
+                completed: true,
+                date: Some(date(day)),
+                value: None,
+            })
+            .collect(),
+    })?;
+    let stats = habit_tracker_service.get_habit_stats(
+        habit.id,
+        DateRange {
+            start: date(7).date_naive(),
+            end: date(8).date_naive(),
+        },
+        StatsGranularity::Week,
+    )?;
+    assert_eq!(
+        stats
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.period.as_str(), bucket.start, bucket.end))
+            .collect::<Vec<_>>(),
+        vec![
+            ("2025-W23", date(7).date_naive(), date(7).date_naive()),
+            ("2025-W24", date(8).date_naive(), date(8).date_naive()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_backup_methods() -> Result<(), Box<dyn Error>> {
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-backups-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let db_path = directory.join("habits.db");
+    let app_config = || -> Result<AppConfig, Box<dyn Error>> {
+        Ok(AppConfig {
+            db_path: db_path.to_string_lossy().into_owned(),
+            backups: BackupConfig {
+                directory: directory.join("backups"),
+                keep_daily: 1,
+                keep_weekly: 0,
+            },
+            ..get_test_app_config()?
+        })
+    };
+
+    // a database with pending migrations is backed up before migrating, new ones are not
+    rusqlite::Connection::open(&db_path)?.execute_batch(include_str!("fixtures/schema_v8.sql"))?;
+    let mut habit_tracker_service = HabitTrackerService::build(app_config()?)?;
+    let backups = list_backups(&habit_tracker_service)?;
+    assert_eq!(
+        backups
+            .iter()
+            .map(|backup| backup.reason)
+            .collect::<Vec<_>>(),
+        vec![BackupReason::PreMigration]
+    );
+    let migrated = rusqlite::Connection::open(directory.join("backups").join(&backups[0].name))?;
+    assert_eq!(
+        migrated.pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0))?,
+        8
+    );
+
+    // only backups taken on start rotate
+    create_backup(&habit_tracker_service, BackupReason::Start)?;
+    create_backup(&habit_tracker_service, BackupReason::Start)?;
+    let manual_backup = create_backup(&habit_tracker_service, BackupReason::Manual)?;
+    assert_eq!(
+        list_backups(&habit_tracker_service)?
+            .iter()
+            .map(|backup| backup.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            BackupReason::Manual,
+            BackupReason::Start,
+            BackupReason::PreMigration
+        ]
+    );
+
+    // restoring backs the current data up first
+    let habit_count = habit_tracker_service.get_habits(true)?.len();
+    habit_tracker_service.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds::default(),
+        title: "Run".to_string(),
+        question: "Did you run?".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+    let previous = restore_backup(&mut habit_tracker_service, &manual_backup.name)?;
+    assert_eq!(previous.reason, BackupReason::PreRestore);
+    assert_eq!(habit_tracker_service.get_habits(true)?.len(), habit_count);
+    restore_backup(&mut habit_tracker_service, &previous.name)?;
+    assert_eq!(
+        habit_tracker_service.get_habits(true)?.len(),
+        habit_count + 1
+    );
+    assert!(matches!(
+        restore_backup(&mut habit_tracker_service, "../habits.db"),
+        Err(HabitTrackerError::NotFound { .. })
+    ));
+
+    // backups from before recurrence rules were stored get them once restored
+    let old_backup = "habits-20250101T000000.000Z-pre-migration.db";
+    rusqlite::Connection::open(directory.join("backups").join(old_backup))?
+        .execute_batch(include_str!("fixtures/schema_v7.sql"))?;
+    restore_backup(&mut habit_tracker_service, old_backup)?;
+    assert_eq!(
+        habit_tracker_service
+            .get_apple_calendar_events()?
+            .iter()
+            .map(|event| (
+                event.recurrence_rule.is_some(),
+                event.recurrence_error.clone()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (true, None),
+            (false, Some("Unsupported frequency HOURLY".to_string()))
+        ]
+    );
+
+    // in-memory databases have no backups
+    let habit_tracker_service = mock_habit_tracker_service()?;
+    assert!(list_backups(&habit_tracker_service)?.is_empty());
+    assert!(matches!(
+        create_backup(&habit_tracker_service, BackupReason::Manual),
+        Err(HabitTrackerError::Storage { .. })
+    ));
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_doctor() -> Result<(), Box<dyn Error>> {
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-doctor-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let db_path = directory.join("habits.db");
+    let mut habit_tracker_service = HabitTrackerService::build(AppConfig {
+        db_path: db_path.to_string_lossy().into_owned(),
+        backups: BackupConfig {
+            directory: directory.join("backups"),
+            keep_daily: 1,
+            keep_weekly: 0,
+        },
+        ..get_test_app_config()?
+    })?;
+    mock_apple_calendar_events(&mut habit_tracker_service, &["eventId"])?;
+    let create_habit =
+        |habit_type: HabitType, event_ids: &[&str], title: &str| CreateHabitRequest {
+            habit_type,
+            event_ids: EventIds {
+                values: event_ids.iter().map(|id| id.to_string()).collect(),
+            },
+            title: title.to_string(),
+            question: format!("{title}?"),
+            kind: HabitKind::YesNo,
+            unit: None,
+            target: None,
+            schedule: Schedule::EveryDay,
+        };
+    let run = habit_tracker_service.create_habit(create_habit(HabitType::Daily, &[], "Run"))?;
+    let meeting = habit_tracker_service.create_habit(create_habit(
+        HabitType::AppleCalendar,
+        &["eventId"],
+        "Meeting",
+    ))?;
+    let read = habit_tracker_service.create_habit(create_habit(HabitType::Daily, &[], "Read"))?;
+    let swim = habit_tracker_service.create_habit(create_habit(
+        HabitType::AppleCalendar,
+        &["eventId"],
+        "Swim",
+    ))?;
+    let day = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+    let entry_id = habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+        data: vec![InsertHabitEntryItem {
+            completed: true,
+            habit_id: run.id,
+            date: Some(habit_tracker_service.date_on_day(day)),
+            value: None,
+        }],
+    })?[0]
+        .id;
+    assert!(habit_tracker_service.doctor(false)?.issues.is_empty());
+
+    // damaged the way older versions or other tools could
+    let conn = rusqlite::Connection::open(&db_path)?;
+    let copy_entry = |habit_id: Option<i64>| -> Result<i64> {
+        conn.execute(
+            "INSERT INTO habitEntry (completed, habitId, date, day) SELECT completed, ?1, date, day FROM habitEntry ORDER BY id LIMIT 1",
+            [habit_id],
+        )?;
+        Ok(conn.last_insert_rowid())
+    };
+    conn.execute("DROP INDEX habitEntry_habitId_day", [])?;
+    let duplicate_id = copy_entry(Some(run.id))?;
+    let orphan_id = copy_entry(Some(999))?;
+    let no_habit_id = copy_entry(None)?;
+    conn.execute(
+        "UPDATE habit SET eventIds = '[\"eventId\", \"missingEventId\"]' WHERE id = ?1",
+        [meeting.id],
+    )?;
+    conn.execute(
+        "UPDATE habit SET eventIds = 'invalid json' WHERE id = ?1",
+        [read.id],
+    )?;
+    conn.execute(
+        "UPDATE habit SET eventIds = '[\"missingEventId\"]' WHERE id = ?1",
+        [swim.id],
+    )?;
+    assert!(habit_tracker_service.get_habits(true).is_err());
+
+    let issues = vec![
+        DoctorIssue::OrphanEntry {
+            entry_id: orphan_id,
+            habit_id: Some(999),
+        },
+        DoctorIssue::OrphanEntry {
+            entry_id: no_habit_id,
+            habit_id: None,
+        },
+        DoctorIssue::DuplicateEntries {
+            habit_id: run.id,
+            day,
+            entry_ids: vec![entry_id, duplicate_id],
+        },
+        DoctorIssue::MissingEvent {
+            habit_id: meeting.id,
+            event_id: "missingEventId".to_string(),
+        },
+        DoctorIssue::InvalidEventIds {
+            habit_id: read.id,
+            column: "eventIds".to_string(),
+            value: "invalid json".to_string(),
+        },
+        DoctorIssue::CalendarHabitWithoutEvents {
+            habit_id: swim.id,
+            value: "[\"missingEventId\"]".to_string(),
+        },
+    ];
+    let report = habit_tracker_service.doctor(false)?;
+    assert_eq!(report.issues, issues);
+    assert!(!report.fixed);
+    assert_eq!(report.backup, None);
+
+    // fixing backs the database up first
+    let report = habit_tracker_service.doctor(true)?;
+    assert_eq!(report.issues, issues);
+    assert!(report.fixed);
+    assert_eq!(report.backup.unwrap().reason, BackupReason::PreRepair);
+    // calendar habits are never left without events
+    assert_eq!(
+        habit_tracker_service.doctor(false)?.issues,
+        issues[issues.len() - 1..]
+    );
+    let habits = habit_tracker_service.get_habits(true)?;
+    assert_eq!(habits[1].event_ids.values, vec!["eventId"]);
+    assert!(habits[2].event_ids.values.is_empty());
+    assert_eq!(habits[3].event_ids.values, vec!["missingEventId"]);
+    habit_tracker_service.delete_habit(swim.id)?;
+    assert!(habit_tracker_service.doctor(false)?.issues.is_empty());
+    // the latest duplicate is kept, and days are unique again
+    let habit_entries = habit_tracker_service.get_habit_entries()?;
+    assert_eq!(
+        habit_entries
+            .iter()
+            .map(|habit_entry| habit_entry.id)
+            .collect::<Vec<_>>(),
+        vec![duplicate_id]
+    );
+    assert!(copy_entry(Some(run.id)).is_err());
+
+    drop(conn);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_services() -> Result<(), Box<dyn Error>> {
+    let directory =
+        std::env::temp_dir().join(format!("habit-tracker-concurrent-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let db_path = directory.join("habits.db");
+    let app_config = || -> Result<AppConfig, Box<dyn Error>> {
+        Ok(AppConfig {
+            db_path: db_path.to_string_lossy().into_owned(),
+            backups: BackupConfig {
+                directory: directory.join("backups"),
+                keep_daily: 1,
+                keep_weekly: 0,
+            },
+            ..get_test_app_config()?
+        })
+    };
+    let habit = HabitTrackerService::build(app_config()?)?.create_habit(CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds::default(),
+        title: "Run".to_string(),
+        question: "Did you run?".to_string(),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    })?;
+
+    // like the app checking in while `habit-sync` syncs the calendar
+    let (check_in_config, sync_config) = (app_config()?, app_config()?);
+    std::thread::scope(|scope| -> Result<(), HabitTrackerError> {
+        let app = scope.spawn(move || -> Result<(), HabitTrackerError> {
+            let mut habit_tracker_service = HabitTrackerService::build(check_in_config)?;
+            let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+            for days in 0..100 {
+                let date = habit_tracker_service.date_on_day(day + Duration::days(days));
+                habit_tracker_service.insert_habit_entries(InsertHabitEntriesRequest {
+                    data: vec![InsertHabitEntryItem {
+                        completed: true,
+                        habit_id: habit.id,
+                        date: Some(date),
+                        value: None,
+                    }],
+                })?;
+            }
+            Ok(())
+        });
+        let sync = scope.spawn(move || -> Result<(), HabitTrackerError> {
+            let mut habit_tracker_service = HabitTrackerService::build(sync_config)?;
+            for count in 1..=50 {
+                habit_tracker_service.sync_calendar_events(
+                    (0..count)
+                        .map(|index| AppleCalendarEvent {
+                            id: format!("eventId{index}"),
+                            name: format!("Event {index}"),
+                            start_date: Utc::now(),
+                            recurrence: "FREQ=DAILY;INTERVAL=1".to_string(),
+                            excluded_dates: ExcludedDates::default(),
+                            parent_id: None,
+                            recurrence_rule: None,
+                            recurrence_error: None,
+                            removed_at: None,
+                        })
+                        .collect(),
+                )?;
+            }
+            Ok(())
+        });
+        app.join().unwrap()?;
+        sync.join().unwrap()
+    })?;
+
+    let habit_tracker_service = HabitTrackerService::build(app_config()?)?;
+    assert_eq!(habit_tracker_service.get_habit_entries()?.len(), 100);
+    assert_eq!(habit_tracker_service.get_apple_calendar_events()?.len(), 50);
+    let conn = rusqlite::Connection::open(&db_path)?;
+    assert_eq!(
+        conn.pragma_query_value(None, "journal_mode", |row| row.get::<_, String>(0))?,
+        "wal"
+    );
+
+    drop((habit_tracker_service, conn));
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_service_pool() -> Result<(), Box<dyn Error>> {
+    let directory = std::env::temp_dir().join(format!("habit-tracker-pool-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let pool = build_service_pool(AppConfig {
+        db_path: directory.join("habits.db").to_string_lossy().into_owned(),
+        ..get_test_app_config()?
+    })?;
+    let create_habit_request = |title: &str| CreateHabitRequest {
+        habit_type: HabitType::Daily,
+        event_ids: EventIds::default(),
+        title: title.to_string(),
+        question: format!("{title}?"),
+        kind: HabitKind::YesNo,
+        unit: None,
+        target: None,
+        schedule: Schedule::EveryDay,
+    };
+
+    // services are used at the same time, and see each other's writes
+    let (reader, writer) = (pool.get()?, pool.get()?);
+    writer.create_habit(create_habit_request("Run"))?;
+    assert_eq!(reader.get_habits(true)?.len(), 1);
+    drop((reader, writer));
+
+    // a panic fails its own call only
+    let panicking_pool = pool.clone();
+    let result = std::thread::spawn(move || {
+        with_service(&panicking_pool, |habit_tracker_service| -> Result<(), _> {
+            habit_tracker_service.create_habit(create_habit_request("Read"))?;
+            panic!("command failed");
+        })
+    })
+    .join();
+    assert!(result.is_err());
+    let titles = with_service(&pool, |habit_tracker_service| {
+        Ok(habit_tracker_service
+            .get_habits(true)?
+            .into_iter()
+            .map(|habit| habit.title)
+            .collect::<Vec<_>>())
+    })?;
+    assert_eq!(titles, vec!["Run", "Read"]);
+
+    // in-memory databases have a single service, as their data is private to its connection
+    let pool = build_service_pool(get_test_app_config()?)?;
+    with_service(&pool, |habit_tracker_service| {
+        habit_tracker_service.create_habit(create_habit_request("Run"))
+    })?;
+    let habits = with_service(&pool, |habit_tracker_service| {
+        habit_tracker_service.get_habits(true)
+    })?;
+    assert_eq!(habits.len(), 1);
+    assert_eq!(pool.max_size(), 1);
+
+    drop(pool);
+    std::fs::remove_dir_all(&directory)?;
     Ok(())
 }
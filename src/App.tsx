@@ -43,11 +43,8 @@ function App() {
     const fetchData = async () => {
       await habitStore.fetchHabits()
       await habitEntryStore.fetchHabitEntries()
-      const appleCalendarFeatureIsEnabled =
-        await appleCalendarEventStore.fetchFeatureStatus()
-      if (appleCalendarFeatureIsEnabled) {
-        await appleCalendarEventStore.fetchCalendarEvents()
-      }
+      await appleCalendarEventStore.fetchFeatureStatus()
+      await appleCalendarEventStore.fetchCalendarEvents()
       setLoading(false)
     }
     fetchData()
@@ -51,12 +51,18 @@ function Dashboard({
   createHabit: (formData: CreateHabitFormData) => Promise<void>
   updateHabit: (formData: UpdateHabitFormData) => Promise<void>
 }) {
-  const { habits } = useHabitStore()
+  const { habits, dueHabitIds } = useHabitStore()
   const { habitEntries, isTrackedToday } = useHabitEntryStore()
   const appleCalendarStore = useAppleCalendarEventStore()
+  // imported .ics events can be linked without the Apple Calendar feature
+  const calendarEventsEnabled =
+    appleCalendarStore.featureEnabled ||
+    appleCalendarStore.calendarEvents.length > 0
   const activeCalendarEvents = appleCalendarStore.calendarEvents.filter(
     (event) =>
-      event.rule.options.until === null || event.rule.options.until > new Date()
+      event.recurrenceError === null &&
+      (event.rule.options.until === null ||
+        event.rule.options.until > new Date())
   )
 
   const [currentContext, setCurrentContext] = useState<
@@ -100,6 +106,7 @@ function Dashboard({
         })
       )
       rruleSet.rrule(new RRule(habit.rule.options))
+      habit.excludedDates.forEach((date) => rruleSet.exdate(date))
     })
 
     return {
@@ -142,9 +149,10 @@ function Dashboard({
                         <p className="overflow-hidden text-ellipsis">
                           {habit.title}
                           {habit.habitType === "AppleCalendar" &&
-                            !activeCalendarEvents.find((event) =>
-                              habit.eventIds.includes(event.id)
-                            ) && (
+                            (habit.removedEventIds.length > 0 ||
+                              !activeCalendarEvents.find((event) =>
+                                habit.eventIds.includes(event.id)
+                              )) && (
                               <CircleAlertIcon
                                 className="text-amber-400"
                                 size={16}
@@ -164,7 +172,7 @@ function Dashboard({
 
         <div className="flex flex-col justify-center items-center gap-1">
           <HabitFormDialog
-            calendarEventsFeatureEnabled={appleCalendarStore.featureEnabled}
+            calendarEventsFeatureEnabled={calendarEventsEnabled}
             activeCalendarEvents={activeCalendarEvents}
             submit={createHabit}
             syncCalendarEvents={appleCalendarStore.syncCalendarEvents}
@@ -173,6 +181,9 @@ function Dashboard({
           {habits.length > 0 && !isTrackedToday && (
             <HabitTrackerFormDialog
               habits={habits.filter((habit) => {
+                if (habit.habitType === "Daily") {
+                  return dueHabitIds.includes(habit.id)
+                }
                 const latestDateFromRuleset = getHabitContext(
                   habit
                 ).rruleSet.before(endOfDay(new Date()), true)
@@ -197,7 +208,7 @@ function Dashboard({
                 key={currentContext.habit.id} // force rerender for proper form update
                 habit={currentContext.habit}
                 submit={updateHabit}
-                calendarEventsFeatureEnabled={appleCalendarStore.featureEnabled}
+                calendarEventsFeatureEnabled={calendarEventsEnabled}
                 activeCalendarEvents={activeCalendarEvents}
                 syncCalendarEvents={appleCalendarStore.syncCalendarEvents}
                 trigger={
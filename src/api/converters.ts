@@ -22,6 +22,10 @@ export const convertFormDataToCreateHabitRequest = (
     event_ids: { values: formData.eventIds },
     title: formData.title,
     question: formData.question,
+    kind: formData.kind,
+    unit: formData.unit,
+    target: formData.target,
+    schedule: formData.schedule,
   }
 }
 
@@ -34,6 +38,10 @@ export const convertFormDataToUpdateHabitRequest = (
     event_ids: { values: formData.eventIds },
     title: formData.title,
     question: formData.question,
+    kind: formData.kind,
+    unit: formData.unit,
+    target: formData.target,
+    schedule: formData.schedule,
   }
 }
 
@@ -44,6 +52,8 @@ export const convertFormDataToInsertHabitEntriesRequest = (
     data: formData.entries.map((entry) => ({
       habit_id: entry.habitId,
       completed: entry.completed,
+      date: null,
+      value: null,
     })),
   }
 }
@@ -55,6 +65,12 @@ export const convertDtoToHabit = (dto: HabitDto): Habit => {
     eventIds: dto.event_ids.values,
     title: dto.title,
     question: dto.question,
+    archivedAt: dto.archived_at ? new Date(dto.archived_at) : null,
+    kind: dto.kind,
+    unit: dto.unit,
+    target: dto.target,
+    schedule: dto.schedule,
+    removedEventIds: dto.removed_event_ids.values,
   }
 }
 
@@ -64,13 +80,18 @@ export const convertDtoToHabitEntry = (dto: HabitEntryDto): HabitEntry => {
     habitId: dto.habit_id,
     completed: dto.completed,
     date: new Date(dto.date),
+    day: dto.day,
+    value: dto.value,
+    timezone: dto.timezone,
   }
 }
 
 export const convertDtoToAppleCalendarEvent = (
   dto: AppleCalendarEventDto
 ): AppleCalendarEvent => {
-  const options = RRule.parseString(dto.recurrence)
+  // flagged rules may not parse here either, they can't be linked to habits anyway
+  const options =
+    dto.recurrence_error === null ? RRule.parseString(dto.recurrence) : {}
   options.dtstart = new Date(dto.start_date)
   // if (!options.until || options.until > new Date()) {
   //   options.until = new Date()
@@ -79,5 +100,7 @@ export const convertDtoToAppleCalendarEvent = (
     id: dto.id,
     name: dto.name,
     rule: new RRule(options),
+    excludedDates: dto.excluded_dates.values.map((date) => new Date(date)),
+    recurrenceError: dto.recurrence_error,
   }
 }
@@ -1,20 +1,84 @@
 // This file has been generated by Specta. DO NOT EDIT.
 
-export type CreateHabitRequest = { habit_type: HabitType; event_ids: EventIds; title: string; question: string }
+export type CreateHabitRequest = { habit_type: HabitType; event_ids: EventIds; title: string; question: string; kind: HabitKind; unit: string | null; target: number | null; schedule: Schedule }
 
-export type HabitEntry = { id: number; habit_id: number; completed: boolean; date: string }
+export type HabitEntry = { id: number; habit_id: number; completed: boolean; date: string; day: string; value: number | null; timezone: string | null }
 
 export type HabitType = "Daily" | "AppleCalendar"
 
+export type HabitKind = "YesNo" | "Measurable"
+
 export type InsertHabitEntriesRequest = { data: InsertHabitEntryItem[] }
 
-export type UpdateHabitRequest = { id: number; habit_type: HabitType; event_ids: EventIds; title: string; question: string }
+export type UpdateHabitRequest = { id: number; habit_type: HabitType; event_ids: EventIds; title: string; question: string; kind: HabitKind; unit: string | null; target: number | null; schedule: Schedule }
+
+export type InsertHabitEntryItem = { habit_id: number; completed: boolean; date: string | null; value: number | null }
 
-export type InsertHabitEntryItem = { habit_id: number; completed: boolean }
+export type AppleCalendarEvent = { id: string; name: string; start_date: string; recurrence: string; excluded_dates: ExcludedDates; parent_id: string | null; recurrence_rule: Recurrence | null; recurrence_error: string | null; removed_at: string | null }
 
-export type AppleCalendarEvent = { id: string; name: string; start_date: string; recurrence: string }
+export type ExcludedDates = { values: string[] }
 
-export type Habit = { id: number; habit_type: HabitType; event_ids: EventIds; title: string; question: string }
+export type Habit = { id: number; habit_type: HabitType; event_ids: EventIds; title: string; question: string; archived_at: string | null; kind: HabitKind; unit: string | null; target: number | null; schedule: Schedule; removed_event_ids: EventIds }
 
 export type EventIds = { values: string[] }
 
+export type UpdateHabitEntryRequest = { id: number; completed: boolean; date: string; value: number | null }
+
+export type HabitStreak = { habit_id: number; current_streak: number; longest_streak: number; last_break_date: string | null }
+
+export type DateRange = { start: string; end: string }
+
+export type StatsGranularity = "Week" | "Month" | "Year"
+
+export type HabitStatsBucket = { period: string; start: string; end: string; completed_count: number; due_count: number; completion_rate: number }
+
+export type HabitStats = { habit_id: number; granularity: StatsGranularity; buckets: HabitStatsBucket[] }
+
+export type DayOfWeek = "Monday" | "Tuesday" | "Wednesday" | "Thursday" | "Friday" | "Saturday" | "Sunday"
+
+export type Schedule = "EveryDay" | { Weekdays: { days: DayOfWeek[] } } | { EveryNDays: { interval: number; start: string } } | { TimesPerWeek: { count: number } } | { TimesPerMonth: { count: number } }
+
+export type HabitTrackerError = { code: "NotFound"; message: string } | { code: "Validation"; message: string; fields: FieldError[] } | { code: "Conflict"; message: string } | { code: "Storage"; message: string } | { code: "CalendarUnavailable"; message: string }
+
+export type FieldError = { field: string; message: string }
+
+export type HabitTrackerArchive = { format_version: number; exported_at: string; habits: Habit[]; habit_entries: HabitEntry[]; calendar_events: ArchivedCalendarEvent[] }
+
+export type ArchivedCalendarEvent = { id: string; name: string; start_date: string; recurrence: string; excluded_dates: ExcludedDates; parent_id: string | null; removed_at: string | null }
+
+export type ImportMode = "Replace" | "Merge"
+
+export type ImportCounts = { added: number; skipped: number; conflicted: number }
+
+export type ImportReport = { habits: ImportCounts; habit_entries: ImportCounts; calendar_events: ImportCounts }
+
+export type CalendarSyncReport = { added: AppleCalendarEvent[]; updated: AppleCalendarEvent[]; removed: AppleCalendarEvent[]; flagged_habits: Habit[] }
+
+export type CsvLayout = "Long" | "Wide"
+
+export type Frequency = "Daily" | "Weekly" | "Monthly" | "Yearly"
+
+export type ByDay = { ordinal: number | null; day: DayOfWeek }
+
+export type Until = string
+
+export type Recurrence = { frequency: Frequency; interval: number; by_day: ByDay[]; by_month_day: number[]; by_month: number[]; by_set_pos: number[]; count: number | null; until: Until | null; week_start: DayOfWeek }
+
+export type ConfigFile = { db_path?: string | null; timezone?: string | null; day_rollover_hour: number; week_start: DayOfWeek; calendar: CalendarSettings; reminders: ReminderSettings; backups: BackupSettings }
+
+export type CalendarSourceKind = "applescript" | "ics" | "fixtures"
+
+export type CalendarSettings = { source: CalendarSourceKind; names: string[]; paths: string[] }
+
+export type ReminderSettings = { sync_time: string; notify_times: string[] }
+
+export type BackupSettings = { directory?: string | null; keep_daily: number; keep_weekly: number }
+
+export type BackupReason = "Start" | "PreMigration" | "PreRestore" | "PreRepair" | "Manual"
+
+export type BackupInfo = { name: string; created_at: string; reason: BackupReason; size: number }
+
+export type DoctorIssue = { Corruption: { message: string } } | { ForeignKeyViolation: { table: string; row_id: number; parent: string } } | { OrphanEntry: { entry_id: number; habit_id: number | null } } | { DuplicateEntries: { habit_id: number; day: string; entry_ids: number[] } } | { InvalidEventIds: { habit_id: number; column: string; value: string } } | { MissingEvent: { habit_id: number; event_id: string } } | { CalendarHabitWithoutEvents: { habit_id: number; value: string } }
+
+export type DoctorReport = { issues: DoctorIssue[]; fixed: boolean; backup?: BackupInfo | null }
+
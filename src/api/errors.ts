@@ -0,0 +1,13 @@
+import { HabitTrackerError } from "./dtos"
+
+// commands reject with a HabitTrackerError, anything else is unexpected
+export const isHabitTrackerError = (
+  error: unknown
+): error is HabitTrackerError => {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error
+  )
+}
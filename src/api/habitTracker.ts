@@ -9,8 +9,21 @@ import {
 } from "./converters"
 import {
   AppleCalendarEvent as AppleCalendarEventDto,
+  BackupInfo,
+  CalendarSyncReport,
+  ConfigFile,
+  CsvLayout,
+  DateRange,
+  DoctorReport,
   Habit as HabitDto,
   HabitEntry as HabitEntryDto,
+  HabitStats,
+  HabitStreak,
+  HabitTrackerArchive,
+  ImportMode,
+  ImportReport,
+  StatsGranularity,
+  UpdateHabitEntryRequest,
 } from "./dtos"
 import {
   CreateHabitFormData,
@@ -24,6 +37,11 @@ export const getHabits = async (): Promise<Habit[]> => {
   return response.map(convertDtoToHabit)
 }
 
+export const getDueHabits = async (day: string): Promise<Habit[]> => {
+  const response = await invoke<HabitDto[]>("get_due_habits", { day })
+  return response.map(convertDtoToHabit)
+}
+
 export const postHabit = async (
   formData: CreateHabitFormData
 ): Promise<Habit> => {
@@ -42,11 +60,48 @@ export const putHabit = async (
   return convertDtoToHabit(response)
 }
 
+export const archiveHabit = async (id: number): Promise<Habit> => {
+  const response = await invoke<HabitDto>("archive_habit", { id })
+  return convertDtoToHabit(response)
+}
+
+export const unarchiveHabit = async (id: number): Promise<Habit> => {
+  const response = await invoke<HabitDto>("unarchive_habit", { id })
+  return convertDtoToHabit(response)
+}
+
+export const deleteHabit = async (id: number): Promise<void> => {
+  await invoke("delete_habit", { id })
+}
+
 export const getHabitEntries = async (): Promise<HabitEntry[]> => {
   const response = await invoke<HabitEntryDto[]>("get_habit_entries")
   return response.map(convertDtoToHabitEntry)
 }
 
+export const getHabitStreaks = async (): Promise<HabitStreak[]> => {
+  return await invoke<HabitStreak[]>("get_habit_streaks")
+}
+
+export const getHabitStats = async (
+  habitId: number,
+  range: DateRange,
+  granularity: StatsGranularity
+): Promise<HabitStats> => {
+  return await invoke<HabitStats>("get_habit_stats", {
+    habitId,
+    range,
+    granularity,
+  })
+}
+
+export const getDueDates = async (
+  habitId: number,
+  range: DateRange
+): Promise<string[]> => {
+  return await invoke<string[]>("get_due_dates", { habitId, range })
+}
+
 export const postHabitEntries = async (
   formData: TrackHabitFormData
 ): Promise<Boolean> => {
@@ -55,6 +110,38 @@ export const postHabitEntries = async (
   })
 }
 
+export const putHabitEntry = async (
+  request: UpdateHabitEntryRequest
+): Promise<HabitEntry> => {
+  const response = await invoke<HabitEntryDto>("update_habit_entry", {
+    request,
+  })
+  return convertDtoToHabitEntry(response)
+}
+
+export const deleteHabitEntry = async (id: number): Promise<void> => {
+  await invoke("delete_habit_entry", { id })
+}
+
+export const exportData = async (): Promise<HabitTrackerArchive> => {
+  return await invoke<HabitTrackerArchive>("export_data")
+}
+
+export const importData = async (
+  archive: HabitTrackerArchive,
+  mode: ImportMode
+): Promise<ImportReport> => {
+  return await invoke<ImportReport>("import_data", { archive, mode })
+}
+
+export const exportCsv = async (
+  path: string,
+  layout: CsvLayout,
+  range: DateRange | null
+): Promise<void> => {
+  await invoke("export_csv", { path, layout, range })
+}
+
 export const getAppleCalendarFeatureStatus = async (): Promise<boolean> => {
   return await invoke<boolean>("get_apple_calendar_feature_status")
 }
@@ -68,11 +155,35 @@ export const getAppleCalendarEvents = async (): Promise<
   return response.map(convertDtoToAppleCalendarEvent)
 }
 
-export const syncAppleCalendarEvents = async (): Promise<
-  AppleCalendarEvent[]
-> => {
-  const response = await invoke<AppleCalendarEventDto[]>(
-    "sync_apple_calendar_events"
-  )
-  return response.map(convertDtoToAppleCalendarEvent)
+export const importIcsCalendar = async (
+  paths: string[]
+): Promise<CalendarSyncReport> => {
+  return await invoke<CalendarSyncReport>("import_ics_calendar", { paths })
+}
+
+export const syncCalendarEvents = async (): Promise<CalendarSyncReport> => {
+  return await invoke<CalendarSyncReport>("sync_calendar_events")
+}
+
+export const getConfig = async (): Promise<ConfigFile> => {
+  return await invoke<ConfigFile>("get_config")
+}
+
+// takes effect on the next start of the app
+export const updateConfig = async (config: ConfigFile): Promise<ConfigFile> => {
+  return await invoke<ConfigFile>("update_config", { config })
+}
+
+export const listBackups = async (): Promise<BackupInfo[]> => {
+  return await invoke<BackupInfo[]>("list_backups")
+}
+
+// returns the backup of the replaced data, to undo the restore with
+export const restoreBackup = async (name: string): Promise<BackupInfo> => {
+  return await invoke<BackupInfo>("restore_backup", { name })
+}
+
+// with `fix`, repairs what it can after backing the database up
+export const doctor = async (fix = false): Promise<DoctorReport> => {
+  return await invoke<DoctorReport>("doctor", { fix })
 }
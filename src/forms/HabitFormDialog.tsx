@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react"
 
 import { HabitFormData, HabitFormDataInput, habitFormSchema } from "./schemas"
+import { isHabitTrackerError } from "@/api/errors"
 
 import { zodResolver } from "@hookform/resolvers/zod"
 import { useForm } from "react-hook-form"
@@ -39,6 +40,14 @@ import { CalendarSyncIcon } from "lucide-react"
 
 import { AppleCalendarEvent, Habit } from "@/models"
 
+// request fields reported by the backend validation, mapped to form fields
+const formFields: Record<string, keyof HabitFormDataInput> = {
+  habit_type: "habitType",
+  event_ids: "eventId",
+  title: "title",
+  question: "question",
+}
+
 function HabitForm({
   habit,
   calendarEventsFeatureEnabled,
@@ -103,14 +112,27 @@ function HabitForm({
       question: formDataInput.question,
       habitType: formDataInput.habitType,
       eventIds: eventIds,
+      kind: habit?.kind ?? "YesNo",
+      unit: habit?.unit ?? null,
+      target: habit?.target ?? null,
+      schedule: habit?.schedule ?? "EveryDay",
     }
-    if (habit) {
-      await submit({ ...formData, id: habit.id })
-    } else {
-      await submit(formData)
-      form.reset()
+    try {
+      if (habit) {
+        await submit({ ...formData, id: habit.id })
+      } else {
+        await submit(formData)
+        form.reset()
+      }
+      setOpenDialog(false)
+    } catch (error) {
+      if (!isHabitTrackerError(error) || error.code !== "Validation") {
+        throw error
+      }
+      error.fields.forEach(({ field, message }) => {
+        form.setError(formFields[field] ?? "root", { message })
+      })
     }
-    setOpenDialog(false)
   }
 
   useEffect(() => {
@@ -266,6 +288,11 @@ function HabitForm({
                 </FormItem>
               )}
             />
+            {form.formState.errors.root && (
+              <p className="text-destructive text-sm">
+                {form.formState.errors.root.message}
+              </p>
+            )}
             <DialogFooter className="mt-8">
               <DialogClose asChild>
                 <Button type="button" variant="outline">
@@ -1,4 +1,5 @@
 import z from "zod"
+import { HabitKind, Schedule } from "../models"
 
 export const habitFormSchema = z
   .object({
@@ -28,6 +29,10 @@ export const habitFormSchema = z
 export type HabitFormDataInput = z.infer<typeof habitFormSchema>
 export type HabitFormData = Omit<HabitFormDataInput, "eventId"> & {
   eventIds: string[]
+  kind: HabitKind
+  unit: string | null
+  target: number | null
+  schedule: Schedule
 }
 export type CreateHabitFormData = HabitFormData
 export type UpdateHabitFormData = HabitFormData & { id: number }
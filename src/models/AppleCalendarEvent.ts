@@ -4,4 +4,6 @@ export type AppleCalendarEvent = {
   id: string
   name: string
   rule: RRule
+  excludedDates: Date[]
+  recurrenceError: string | null
 }
@@ -1,4 +1,6 @@
+import { HabitKind } from "./HabitKind"
 import { HabitType } from "./HabitType"
+import { Schedule } from "./Schedule"
 
 export type Habit = {
   id: number
@@ -6,4 +8,10 @@ export type Habit = {
   eventIds: string[]
   title: string
   question: string
+  archivedAt: Date | null
+  kind: HabitKind
+  unit: string | null
+  target: number | null
+  schedule: Schedule
+  removedEventIds: string[]
 }
@@ -3,4 +3,7 @@ export type HabitEntry = {
   habitId: number
   completed: boolean
   date: Date
+  day: string
+  value: number | null
+  timezone: string | null
 }
@@ -0,0 +1 @@
+export type HabitKind = "YesNo" | "Measurable"
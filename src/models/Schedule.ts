@@ -0,0 +1,15 @@
+export type DayOfWeek =
+  | "Monday"
+  | "Tuesday"
+  | "Wednesday"
+  | "Thursday"
+  | "Friday"
+  | "Saturday"
+  | "Sunday"
+
+export type Schedule =
+  | "EveryDay"
+  | { Weekdays: { days: DayOfWeek[] } }
+  | { EveryNDays: { interval: number; start: string } }
+  | { TimesPerWeek: { count: number } }
+  | { TimesPerMonth: { count: number } }
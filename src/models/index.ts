@@ -1,4 +1,6 @@
 export * from "./AppleCalendarEvent"
 export * from "./Habit"
 export * from "./HabitEntry"
+export * from "./HabitKind"
 export * from "./HabitType"
+export * from "./Schedule"
@@ -3,10 +3,12 @@ import { create } from "zustand"
 import {
   getAppleCalendarEvents,
   getAppleCalendarFeatureStatus,
-  syncAppleCalendarEvents,
+  importIcsCalendar,
+  syncCalendarEvents,
 } from "../api/habitTracker"
 
 import { AppleCalendarEvent } from "../models"
+import { useHabitStore } from "./habit"
 
 interface State {
   featureEnabled: boolean
@@ -17,9 +19,10 @@ interface Action {
   fetchFeatureStatus: () => Promise<boolean>
   fetchCalendarEvents: () => Promise<void>
   syncCalendarEvents: () => Promise<void>
+  importIcsCalendarEvents: (paths: string[]) => Promise<void>
 }
 
-export const useAppleCalendarEventStore = create<State & Action>((set) => ({
+export const useAppleCalendarEventStore = create<State & Action>((set, get) => ({
   featureEnabled: false,
   calendarEvents: [],
   fetchFeatureStatus: async (): Promise<boolean> => {
@@ -32,7 +35,17 @@ export const useAppleCalendarEventStore = create<State & Action>((set) => ({
     set({ calendarEvents })
   },
   syncCalendarEvents: async () => {
-    const calendarEvents = await syncAppleCalendarEvents()
-    set({ calendarEvents })
+    // without a calendar source, events only change on .ics imports
+    if (get().featureEnabled) {
+      await syncCalendarEvents()
+    }
+    await get().fetchCalendarEvents()
+    // habits linked to removed events got flagged
+    await useHabitStore.getState().fetchHabits()
+  },
+  importIcsCalendarEvents: async (paths: string[]) => {
+    await importIcsCalendar(paths)
+    await get().fetchCalendarEvents()
+    await useHabitStore.getState().fetchHabits()
   },
 }))
@@ -1,6 +1,12 @@
 import { create } from "zustand"
 
-import { getHabits, postHabit, putHabit } from "../api/habitTracker"
+import { format } from "date-fns"
+import {
+  getDueHabits,
+  getHabits,
+  postHabit,
+  putHabit,
+} from "../api/habitTracker"
 
 import { CreateHabitFormData, UpdateHabitFormData } from "../forms/schemas"
 
@@ -8,25 +14,34 @@ import { Habit } from "../models"
 
 interface State {
   habits: Habit[]
+  dueHabitIds: number[]
 }
 
 interface Action {
   fetchHabits: () => Promise<void>
+  fetchDueHabits: () => Promise<void>
   createHabit: (formData: CreateHabitFormData) => Promise<void>
   updateHabit: (formData: UpdateHabitFormData) => Promise<void>
 }
 
-export const useHabitStore = create<State & Action>((set) => ({
+export const useHabitStore = create<State & Action>((set, get) => ({
   habits: [],
+  dueHabitIds: [],
   fetchHabits: async () => {
     const habits = await getHabits()
     set({ habits })
+    await get().fetchDueHabits()
+  },
+  fetchDueHabits: async () => {
+    const dueHabits = await getDueHabits(format(new Date(), "yyyy-MM-dd"))
+    set({ dueHabitIds: dueHabits.map((habit) => habit.id) })
   },
   createHabit: async (formData: CreateHabitFormData) => {
     const habit = await postHabit(formData)
     set((state) => ({
       habits: [...state.habits, habit],
     }))
+    await get().fetchDueHabits()
   },
   updateHabit: async (formData: UpdateHabitFormData) => {
     const updatedHabit = await putHabit(formData)
@@ -35,5 +50,6 @@ export const useHabitStore = create<State & Action>((set) => ({
         habit.id === updatedHabit.id ? updatedHabit : habit
       ),
     }))
+    await get().fetchDueHabits()
   },
 }))